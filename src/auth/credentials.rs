@@ -0,0 +1,352 @@
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use age::armor::{ArmoredReader, ArmoredWriter, Format};
+use age::secrecy::SecretString;
+use age::{Decryptor, Encryptor, IdentityFile};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::config::{CredentialEncryption, CredentialsConfig};
+
+/// Tokens this close to expiry are treated as expired
+const EXPIRY_MARGIN_SECS: u64 = 60;
+
+/// Passphrase for an encrypted credentials file, read instead of prompting
+pub const PASSPHRASE_ENV: &str = "REPO_SEARCH_PASSPHRASE";
+
+/// Tokens obtained by `auth login`, kept apart from `config.toml`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CredentialStore {
+    #[serde(default)]
+    providers: BTreeMap<String, StoredCredential>,
+
+    /// File the store was loaded from
+    #[serde(skip)]
+    path: Option<PathBuf>,
+
+    /// How the file is encrypted, if at all
+    #[serde(skip)]
+    key: Option<StoreKey>,
+
+    /// Plaintext file to delete once the store has been saved encrypted
+    #[serde(skip)]
+    plaintext_path: Option<PathBuf>,
+}
+
+/// Key material for an encrypted store
+#[derive(Debug, Clone)]
+enum StoreKey {
+    /// Asked for on first use and kept for the rest of the run
+    Passphrase(Option<SecretString>),
+    /// age identity file; its recipients are used to encrypt
+    Identity(PathBuf),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StoredCredential {
+    pub access_token: String,
+    pub refresh_token: Option<String>,
+    /// Expiry as seconds since the Unix epoch
+    pub expires_at: Option<u64>,
+}
+
+impl StoredCredential {
+    pub fn is_expired(&self) -> bool {
+        self.expires_at
+            .is_some_and(|expires_at| expires_at <= now() + EXPIRY_MARGIN_SECS)
+    }
+}
+
+impl CredentialStore {
+    pub fn default_path() -> Result<PathBuf> {
+        let config_dir = dirs::config_dir()
+            .ok_or_else(|| anyhow::anyhow!("Could not determine config directory"))?;
+        Ok(config_dir.join("repo_search").join("credentials.toml"))
+    }
+
+    /// Encrypted counterpart of `default_path`
+    pub fn encrypted_path() -> Result<PathBuf> {
+        Ok(Self::default_path()?.with_extension("toml.age"))
+    }
+
+    /// Load the store described by the `[credentials]` settings
+    pub fn load(settings: &CredentialsConfig) -> Result<Self> {
+        let key = match settings.encryption {
+            None => return Self::load_from(&Self::default_path()?),
+            Some(CredentialEncryption::Passphrase) => StoreKey::Passphrase(None),
+            Some(CredentialEncryption::Age) => {
+                let identity = settings.identity.clone().ok_or_else(|| {
+                    anyhow::anyhow!("credentials.identity must be set when encryption = \"age\"")
+                })?;
+                StoreKey::Identity(identity)
+            }
+        };
+        Self::load_encrypted(&Self::encrypted_path()?, &Self::default_path()?, key)
+    }
+
+    fn load_from(path: &Path) -> Result<Self> {
+        let mut store = if path.exists() {
+            let content = fs::read_to_string(path)
+                .with_context(|| format!("Failed to read {}", path.display()))?;
+            toml::from_str(&content)
+                .with_context(|| format!("Invalid credentials file {}", path.display()))?
+        } else {
+            Self::default()
+        };
+        store.path = Some(path.to_path_buf());
+        Ok(store)
+    }
+
+    /// Decrypt `path`, or start from the plaintext file so that the next save
+    /// moves its tokens into the encrypted one
+    fn load_encrypted(path: &Path, plaintext: &Path, mut key: StoreKey) -> Result<Self> {
+        let mut store = if path.exists() {
+            let armored =
+                fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+            let content = key
+                .decrypt(&armored)
+                .with_context(|| format!("Failed to decrypt {}", path.display()))?;
+            toml::from_str(&content)
+                .with_context(|| format!("Invalid credentials file {}", path.display()))?
+        } else if plaintext.exists() {
+            let mut store = Self::load_from(plaintext)?;
+            store.plaintext_path = Some(plaintext.to_path_buf());
+            store
+        } else {
+            Self::default()
+        };
+        store.path = Some(path.to_path_buf());
+        store.key = Some(key);
+        Ok(store)
+    }
+
+    pub fn save(&mut self) -> Result<()> {
+        let path = match &self.path {
+            Some(path) => path.clone(),
+            None => Self::default_path()?,
+        };
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let content = toml::to_string(self)?;
+        let content = match &mut self.key {
+            Some(key) => key.encrypt(&content)?,
+            None => content.into_bytes(),
+        };
+        crate::security::write_private(&path, content)
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+
+        if let Some(plaintext) = self.plaintext_path.take() {
+            fs::remove_file(&plaintext)
+                .with_context(|| format!("Failed to remove {}", plaintext.display()))?;
+        }
+        Ok(())
+    }
+
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    pub fn is_encrypted(&self) -> bool {
+        self.key.is_some()
+    }
+
+    pub fn get(&self, name: &str) -> Option<&StoredCredential> {
+        self.providers.get(name)
+    }
+
+    pub fn set(&mut self, name: &str, credential: StoredCredential) {
+        self.providers.insert(name.to_string(), credential);
+    }
+
+    pub fn remove(&mut self, name: &str) -> bool {
+        self.providers.remove(name).is_some()
+    }
+}
+
+impl StoreKey {
+    fn decrypt(&mut self, armored: &[u8]) -> Result<String> {
+        let decryptor = Decryptor::new_buffered(ArmoredReader::new(armored))?;
+        let mut reader = match self {
+            StoreKey::Passphrase(cached) => {
+                let passphrase = match cached {
+                    Some(passphrase) => passphrase.clone(),
+                    None => read_passphrase(false)?,
+                };
+                let identity = age::scrypt::Identity::new(passphrase.clone());
+                let reader = decryptor.decrypt(std::iter::once(&identity as _))?;
+                *cached = Some(passphrase);
+                reader
+            }
+            StoreKey::Identity(path) => {
+                let identities = identity_file(path)?.into_identities()?;
+                decryptor.decrypt(identities.iter().map(|identity| identity.as_ref() as _))?
+            }
+        };
+
+        let mut content = String::new();
+        reader.read_to_string(&mut content)?;
+        Ok(content)
+    }
+
+    fn encrypt(&mut self, content: &str) -> Result<Vec<u8>> {
+        let encryptor = match self {
+            StoreKey::Passphrase(cached) => {
+                let passphrase = match cached {
+                    Some(passphrase) => passphrase.clone(),
+                    None => read_passphrase(true)?,
+                };
+                *cached = Some(passphrase.clone());
+                Encryptor::with_user_passphrase(passphrase)
+            }
+            StoreKey::Identity(path) => {
+                let recipients = identity_file(path)?.to_recipients()?;
+                Encryptor::with_recipients(
+                    recipients.iter().map(|recipient| recipient.as_ref() as _),
+                )?
+            }
+        };
+
+        let mut output = Vec::new();
+        let armored = ArmoredWriter::wrap_output(&mut output, Format::AsciiArmor)?;
+        let mut writer = encryptor.wrap_output(armored)?;
+        writer.write_all(content.as_bytes())?;
+        writer.finish()?.finish()?;
+        Ok(output)
+    }
+}
+
+fn identity_file(path: &Path) -> Result<IdentityFile<age::NoCallbacks>> {
+    IdentityFile::from_file(path.to_string_lossy().into_owned())
+        .with_context(|| format!("Failed to read age identity {}", path.display()))
+}
+
+/// Read the store passphrase from the environment or the terminal, asking
+/// twice when it is about to protect a new file
+fn read_passphrase(confirm: bool) -> Result<SecretString> {
+    if let Ok(passphrase) = env::var(PASSPHRASE_ENV) {
+        return Ok(passphrase.into());
+    }
+
+    let passphrase = rpassword::prompt_password("Credentials passphrase: ")?;
+    if passphrase.is_empty() {
+        anyhow::bail!("A passphrase is required to use the encrypted credentials file");
+    }
+    if confirm && rpassword::prompt_password("Repeat passphrase: ")? != passphrase {
+        anyhow::bail!("Passphrases do not match");
+    }
+    Ok(passphrase.into())
+}
+
+/// Current time as seconds since the Unix epoch
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// Format Unix seconds as `YYYY-MM-DD HH:MM:SS UTC`
+pub fn format_timestamp(timestamp: u64) -> String {
+    let days = (timestamp / 86_400) as i64;
+    let secs = timestamp % 86_400;
+
+    // Civil date from days since the epoch (proleptic Gregorian)
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02} UTC",
+        year,
+        month,
+        day,
+        secs / 3600,
+        secs % 3600 / 60,
+        secs % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use age::secrecy::ExposeSecret;
+
+    fn credential(token: &str) -> StoredCredential {
+        StoredCredential {
+            access_token: token.to_string(),
+            refresh_token: None,
+            expires_at: None,
+        }
+    }
+
+    #[test]
+    fn test_age_store_round_trip() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+        let identity_path = dir.join("identity.txt");
+        let identity = age::x25519::Identity::generate();
+        fs::write(&identity_path, identity.to_string().expose_secret()).unwrap();
+
+        let path = dir.join("credentials.toml.age");
+        let plaintext = dir.join("credentials.toml");
+        let key = StoreKey::Identity(identity_path.clone());
+        let mut store = CredentialStore::load_encrypted(&path, &plaintext, key.clone()).unwrap();
+        store.set("github", credential("secret-token"));
+        store.save().unwrap();
+
+        let written = fs::read_to_string(&path).unwrap();
+        assert!(written.starts_with("-----BEGIN AGE ENCRYPTED FILE-----"));
+        assert!(!written.contains("secret-token"));
+
+        let loaded = CredentialStore::load_encrypted(&path, &plaintext, key).unwrap();
+        assert_eq!(loaded.get("github"), Some(&credential("secret-token")));
+        assert!(loaded.is_encrypted());
+    }
+
+    #[test]
+    fn test_encrypted_store_takes_over_plaintext_file() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+        let path = dir.join("credentials.toml.age");
+        let plaintext = dir.join("credentials.toml");
+        fs::write(
+            &plaintext,
+            "[providers.gitlab]\naccess_token = \"old-token\"\n",
+        )
+        .unwrap();
+
+        let key = StoreKey::Passphrase(Some("correct horse".to_string().into()));
+        let mut store = CredentialStore::load_encrypted(&path, &plaintext, key).unwrap();
+        assert_eq!(store.get("gitlab"), Some(&credential("old-token")));
+        store.save().unwrap();
+        assert!(!plaintext.exists());
+
+        let wrong = StoreKey::Passphrase(Some("wrong".to_string().into()));
+        assert!(CredentialStore::load_encrypted(&path, &plaintext, wrong).is_err());
+
+        let key = StoreKey::Passphrase(Some("correct horse".to_string().into()));
+        let loaded = CredentialStore::load_encrypted(&path, &plaintext, key).unwrap();
+        assert_eq!(loaded.get("gitlab"), Some(&credential("old-token")));
+    }
+
+    #[test]
+    fn test_format_timestamp() {
+        assert_eq!(format_timestamp(0), "1970-01-01 00:00:00 UTC");
+        assert_eq!(format_timestamp(1_468_275_250), "2016-07-11 22:14:10 UTC");
+        assert_eq!(format_timestamp(1_709_208_000), "2024-02-29 12:00:00 UTC");
+    }
+}
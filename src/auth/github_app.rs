@@ -0,0 +1,325 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::sync::Mutex;
+
+use anyhow::{Context, Result};
+use jsonwebtoken::{Algorithm, EncodingKey, Header};
+use reqwest::{Client, StatusCode};
+use serde::{Deserialize, Serialize};
+
+use super::credentials::now;
+use crate::config::{GitHubAppConfig, ResolvedProvider};
+
+/// GitHub rejects app JWTs that live longer than ten minutes
+const JWT_LIFETIME_SECS: u64 = 540;
+/// Cached installation tokens this close to expiry are replaced
+const EXPIRY_MARGIN_SECS: u64 = 300;
+
+#[derive(Debug, Serialize)]
+struct Claims {
+    iat: u64,
+    exp: u64,
+    iss: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct Installation {
+    id: u64,
+    account: Option<InstallationAccount>,
+}
+
+#[derive(Debug, Deserialize)]
+struct InstallationAccount {
+    login: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct App {
+    slug: String,
+}
+
+/// What `auth status` reports for a GitHub App provider
+#[derive(Debug, Clone)]
+pub struct AppStatus {
+    pub slug: String,
+    pub installation_id: u64,
+    /// Organization or user the app is installed on
+    pub account: Option<String>,
+    pub rate_limit_remaining: Option<u64>,
+}
+
+#[derive(Debug, Deserialize)]
+struct InstallationToken {
+    token: String,
+    expires_at: String,
+}
+
+/// Installation tokens for this run, keyed by API URL, app and installation.
+/// They are never written to disk; a new run exchanges the JWT again.
+static TOKENS: Mutex<BTreeMap<String, CachedToken>> = Mutex::new(BTreeMap::new());
+
+#[derive(Debug, Clone)]
+struct CachedToken {
+    token: String,
+    /// Expiry as seconds since the Unix epoch
+    expires_at: u64,
+}
+
+/// Get an installation token for `provider`, picking the installation for
+/// `owner` (falling back to the provider's scope owner, then `installation_id`)
+pub async fn installation_token(
+    provider: &ResolvedProvider,
+    app: &GitHubAppConfig,
+    owner: Option<&str>,
+) -> Result<String> {
+    let client = Client::new();
+    let jwt = app_jwt(app)?;
+    let installation_id = pick_installation(&client, provider, app, &jwt, owner).await?;
+
+    let key = format!("{}#{}#{}", provider.url, app.app_id, installation_id);
+    let cached = TOKENS
+        .lock()
+        .ok()
+        .and_then(|tokens| tokens.get(&key).cloned());
+    if let Some(cached) = cached {
+        if cached.expires_at > now() + EXPIRY_MARGIN_SECS {
+            return Ok(cached.token);
+        }
+    }
+
+    let url = format!(
+        "{}/app/installations/{}/access_tokens",
+        provider.url, installation_id
+    );
+    let response = app_request(client.post(&url), &jwt)
+        .send()
+        .await
+        .context("Failed to create GitHub App installation token")?;
+    if !response.status().is_success() {
+        anyhow::bail!(
+            "GitHub App installation token request failed ({})",
+            response.status()
+        );
+    }
+    let token: InstallationToken = response
+        .json()
+        .await
+        .context("Failed to parse installation token response")?;
+
+    // Installation tokens last an hour; fall back to that if the timestamp is odd
+    let expires_at = parse_timestamp(&token.expires_at).unwrap_or(now() + 3600);
+    if let Ok(mut tokens) = TOKENS.lock() {
+        tokens.retain(|_, cached| cached.expires_at > now());
+        tokens.insert(
+            key,
+            CachedToken {
+                token: token.token.clone(),
+                expires_at,
+            },
+        );
+    }
+
+    Ok(token.token)
+}
+
+/// Describe the app and installation `provider` authenticates as. Installation
+/// tokens can't read `/user`, so the app is looked up with its JWT and the rate
+/// limit with the token from `apply_github_apps`.
+pub async fn status(provider: &ResolvedProvider, app: &GitHubAppConfig) -> Result<AppStatus> {
+    let client = Client::new();
+    let jwt = app_jwt(app)?;
+    let installation_id = pick_installation(&client, provider, app, &jwt, None).await?;
+
+    let app_info: App = get_json(
+        app_request(client.get(format!("{}/app", provider.url)), &jwt),
+        "GitHub App",
+    )
+    .await?;
+    let installation: Installation = get_json(
+        app_request(
+            client.get(format!(
+                "{}/app/installations/{}",
+                provider.url, installation_id
+            )),
+            &jwt,
+        ),
+        "GitHub App installation",
+    )
+    .await?;
+
+    let rate_limit_remaining = match &provider.token {
+        Some(token) => {
+            let rate_limit: serde_json::Value = get_json(
+                app_request(client.get(format!("{}/rate_limit", provider.url)), token),
+                "GitHub rate limit",
+            )
+            .await?;
+            rate_limit
+                .pointer("/resources/core/remaining")
+                .and_then(serde_json::Value::as_u64)
+        }
+        None => None,
+    };
+
+    Ok(AppStatus {
+        slug: app_info.slug,
+        installation_id,
+        account: installation.account.map(|account| account.login),
+        rate_limit_remaining,
+    })
+}
+
+async fn get_json<T: serde::de::DeserializeOwned>(
+    request: reqwest::RequestBuilder,
+    what: &str,
+) -> Result<T> {
+    let response = request
+        .send()
+        .await
+        .with_context(|| format!("Failed to fetch {}", what))?;
+    if !response.status().is_success() {
+        anyhow::bail!("{} request failed ({})", what, response.status());
+    }
+    response
+        .json()
+        .await
+        .with_context(|| format!("Failed to parse {}", what))
+}
+
+/// The installation for `owner` (falling back to the provider's scope owner),
+/// else the configured `installation_id`, else the app's only installation
+async fn pick_installation(
+    client: &Client,
+    provider: &ResolvedProvider,
+    app: &GitHubAppConfig,
+    jwt: &str,
+    owner: Option<&str>,
+) -> Result<u64> {
+    match (
+        owner.or(provider.scope.owner.as_deref()),
+        app.installation_id,
+    ) {
+        (Some(owner), _) => find_installation(client, &provider.url, jwt, owner).await,
+        (None, Some(id)) => Ok(id),
+        (None, None) => single_installation(client, &provider.url, jwt).await,
+    }
+}
+
+fn app_jwt(app: &GitHubAppConfig) -> Result<String> {
+    let pem = fs::read(&app.private_key_path).with_context(|| {
+        format!(
+            "Failed to read GitHub App private key {}",
+            app.private_key_path.display()
+        )
+    })?;
+    let key = EncodingKey::from_rsa_pem(&pem).context("Invalid GitHub App private key")?;
+
+    // Backdate to allow for clock drift between us and GitHub
+    let issued_at = now().saturating_sub(60);
+    let claims = Claims {
+        iat: issued_at,
+        exp: issued_at + JWT_LIFETIME_SECS,
+        iss: app.app_id.to_string(),
+    };
+    jsonwebtoken::encode(&Header::new(Algorithm::RS256), &claims, &key)
+        .context("Failed to sign GitHub App JWT")
+}
+
+fn app_request(request: reqwest::RequestBuilder, jwt: &str) -> reqwest::RequestBuilder {
+    request
+        .header("Authorization", format!("Bearer {}", jwt))
+        .header("User-Agent", "repo_search_cli")
+        .header("Accept", "application/vnd.github+json")
+}
+
+/// Look up the app's installation on an org, falling back to a user account
+async fn find_installation(client: &Client, base_url: &str, jwt: &str, owner: &str) -> Result<u64> {
+    for kind in ["orgs", "users"] {
+        let url = format!("{}/{}/{}/installation", base_url, kind, owner);
+        let response = app_request(client.get(&url), jwt)
+            .send()
+            .await
+            .context("Failed to look up GitHub App installation")?;
+
+        if response.status() == StatusCode::NOT_FOUND {
+            continue;
+        }
+        if !response.status().is_success() {
+            anyhow::bail!(
+                "GitHub App installation lookup failed ({})",
+                response.status()
+            );
+        }
+        let installation: Installation = response.json().await?;
+        return Ok(installation.id);
+    }
+
+    anyhow::bail!("GitHub App is not installed on '{}'", owner)
+}
+
+async fn single_installation(client: &Client, base_url: &str, jwt: &str) -> Result<u64> {
+    let url = format!("{}/app/installations", base_url);
+    let response = app_request(client.get(&url), jwt)
+        .send()
+        .await
+        .context("Failed to list GitHub App installations")?;
+    if !response.status().is_success() {
+        anyhow::bail!(
+            "GitHub App installation list failed ({})",
+            response.status()
+        );
+    }
+
+    let installations: Vec<Installation> = response.json().await?;
+    match installations.as_slice() {
+        [installation] => Ok(installation.id),
+        [] => anyhow::bail!("GitHub App has no installations"),
+        _ => anyhow::bail!(
+            "GitHub App has several installations; set github_app.installation_id or use --owner"
+        ),
+    }
+}
+
+/// Parse GitHub's `YYYY-MM-DDTHH:MM:SSZ` timestamps into Unix seconds
+fn parse_timestamp(timestamp: &str) -> Option<u64> {
+    let (date, time) = timestamp.trim_end_matches('Z').split_once('T')?;
+    let mut date_parts = date.split('-').map(|part| part.parse::<i64>());
+    let (year, month, day) = (
+        date_parts.next()?.ok()?,
+        date_parts.next()?.ok()?,
+        date_parts.next()?.ok()?,
+    );
+    let mut time_parts = time.split(':').map(|part| part.parse::<i64>());
+    let (hour, minute, second) = (
+        time_parts.next()?.ok()?,
+        time_parts.next()?.ok()?,
+        time_parts.next()?.ok()?,
+    );
+
+    // Days since the epoch for a proleptic Gregorian date
+    let (y, m) = if month <= 2 {
+        (year - 1, month + 9)
+    } else {
+        (year, month - 3)
+    };
+    let era = y.div_euclid(400);
+    let year_of_era = y - era * 400;
+    let day_of_year = (153 * m + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    let days = era * 146_097 + day_of_era - 719_468;
+
+    u64::try_from(days * 86_400 + hour * 3600 + minute * 60 + second).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_timestamp() {
+        assert_eq!(parse_timestamp("1970-01-01T00:00:00Z"), Some(0));
+        assert_eq!(parse_timestamp("2016-07-11T22:14:10Z"), Some(1_468_275_250));
+        assert_eq!(parse_timestamp("2024-02-29T12:00:00Z"), Some(1_709_208_000));
+        assert_eq!(parse_timestamp("not a date"), None);
+    }
+}
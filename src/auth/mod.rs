@@ -0,0 +1,91 @@
+pub mod credentials;
+pub mod github_app;
+pub mod oauth;
+
+use crate::config::{Config, ResolvedProvider};
+use crate::security::redact;
+use credentials::CredentialStore;
+
+/// Exchange GitHub App credentials for installation tokens. Providers whose
+/// token cannot be obtained are removed, with an error for each.
+pub async fn apply_github_apps(
+    providers: &mut Vec<ResolvedProvider>,
+    owner: Option<&str>,
+) -> Vec<String> {
+    let mut errors = Vec::new();
+    let mut ready = Vec::with_capacity(providers.len());
+
+    for mut provider in providers.drain(..) {
+        let Some(app) = provider.github_app.clone() else {
+            ready.push(provider);
+            continue;
+        };
+        match github_app::installation_token(&provider, &app, owner).await {
+            Ok(token) => {
+                provider.token = Some(token);
+                ready.push(provider);
+            }
+            Err(e) => errors.push(redact(
+                &format!("{}: {}", provider.name, e),
+                &provider.secrets(),
+            )),
+        }
+    }
+
+    *providers = ready;
+    errors
+}
+
+/// Refresh stored OAuth tokens that have expired, saving the new tokens.
+/// Failures are returned as warnings so the search can still run.
+pub async fn refresh_expired(providers: &mut [ResolvedProvider], config: &Config) -> Vec<String> {
+    let mut warnings = Vec::new();
+    let mut store: Option<CredentialStore> = None;
+
+    for provider in providers.iter_mut() {
+        let Some(credential) = &provider.credential else {
+            continue;
+        };
+        if !credential.is_expired() {
+            continue;
+        }
+        if credential.refresh_token.is_none() {
+            warnings.push(format!(
+                "{}: stored token has expired; run `repo_search auth login {}`",
+                provider.name, provider.name
+            ));
+            continue;
+        }
+
+        match oauth::refresh(provider, credential).await {
+            Ok(refreshed) => {
+                provider.token = Some(refreshed.access_token.clone());
+                provider.credential = Some(refreshed.clone());
+
+                let store = match &mut store {
+                    Some(store) => store,
+                    None => match config.credential_store() {
+                        Ok(loaded) => store.insert(loaded),
+                        Err(e) => {
+                            warnings.push(format!("Failed to load credentials: {}", e));
+                            continue;
+                        }
+                    },
+                };
+                store.set(&config.credential_key(&provider.name), refreshed);
+            }
+            Err(e) => warnings.push(redact(
+                &format!("{}: failed to refresh token: {}", provider.name, e),
+                &provider.secrets(),
+            )),
+        }
+    }
+
+    if let Some(mut store) = store {
+        if let Err(e) = store.save() {
+            warnings.push(format!("Failed to save refreshed credentials: {}", e));
+        }
+    }
+
+    warnings
+}
@@ -0,0 +1,250 @@
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use reqwest::Client;
+use serde::Deserialize;
+
+use super::credentials::{now, StoredCredential};
+use crate::config::{ProviderType, ResolvedProvider};
+
+const DEVICE_GRANT_TYPE: &str = "urn:ietf:params:oauth:grant-type:device_code";
+
+#[derive(Debug, Deserialize)]
+struct DeviceCodeResponse {
+    device_code: String,
+    user_code: String,
+    verification_uri: String,
+    verification_uri_complete: Option<String>,
+    expires_in: u64,
+    interval: Option<u64>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TokenResponse {
+    access_token: Option<String>,
+    refresh_token: Option<String>,
+    expires_in: Option<u64>,
+    error: Option<String>,
+    error_description: Option<String>,
+}
+
+impl TokenResponse {
+    fn into_credential(self, previous_refresh: Option<&str>) -> Result<StoredCredential> {
+        let access_token = match self.access_token {
+            Some(token) => token,
+            None => anyhow::bail!("{}", self.error_message()),
+        };
+        Ok(StoredCredential {
+            access_token,
+            // Some providers only return a refresh token on the first grant
+            refresh_token: self
+                .refresh_token
+                .or_else(|| previous_refresh.map(str::to_string)),
+            expires_at: self.expires_in.map(|secs| now() + secs),
+        })
+    }
+
+    fn error_message(&self) -> String {
+        match (&self.error, &self.error_description) {
+            (Some(error), Some(description)) => format!("{}: {}", error, description),
+            (Some(error), None) => error.clone(),
+            _ => "no access token in response".to_string(),
+        }
+    }
+}
+
+/// Run the interactive login flow for `provider` and return the new credential
+pub async fn login(provider: &ResolvedProvider) -> Result<StoredCredential> {
+    let client_id = provider.oauth.client_id.as_deref().ok_or_else(|| {
+        anyhow::anyhow!(
+            "No OAuth client_id configured; set providers.{}.client_id",
+            provider.name
+        )
+    })?;
+    let client = Client::new();
+
+    match provider.provider_type {
+        ProviderType::Github => {
+            let web_url = github_web_url(&provider.url);
+            device_flow(
+                &client,
+                &format!("{}/login/device/code", web_url),
+                &format!("{}/login/oauth/access_token", web_url),
+                client_id,
+                "repo read:org",
+            )
+            .await
+        }
+        ProviderType::Gitlab => {
+            device_flow(
+                &client,
+                &format!("{}/oauth/authorize_device", provider.url),
+                &format!("{}/oauth/token", provider.url),
+                client_id,
+                "read_api read_user",
+            )
+            .await
+        }
+        ProviderType::Bitbucket => {
+            // Bitbucket has no device flow; OAuth consumers use client credentials
+            let client_secret = bitbucket_secret(provider)?;
+            let response = client
+                .post(bitbucket_token_url(&provider.url))
+                .basic_auth(client_id, Some(client_secret))
+                .header("Accept", "application/json")
+                .form(&[("grant_type", "client_credentials")])
+                .send()
+                .await
+                .context("Failed to request Bitbucket token")?;
+            let token: TokenResponse = response
+                .json()
+                .await
+                .context("Failed to parse Bitbucket token response")?;
+            token.into_credential(None)
+        }
+    }
+}
+
+/// Exchange a stored refresh token for a new access token
+pub async fn refresh(
+    provider: &ResolvedProvider,
+    credential: &StoredCredential,
+) -> Result<StoredCredential> {
+    let refresh_token = credential
+        .refresh_token
+        .as_deref()
+        .ok_or_else(|| anyhow::anyhow!("No refresh token stored"))?;
+    let client_id = provider
+        .oauth
+        .client_id
+        .as_deref()
+        .ok_or_else(|| anyhow::anyhow!("No OAuth client_id configured"))?;
+    let client = Client::new();
+
+    let request = match provider.provider_type {
+        ProviderType::Github => client
+            .post(format!(
+                "{}/login/oauth/access_token",
+                github_web_url(&provider.url)
+            ))
+            .form(&[
+                ("grant_type", "refresh_token"),
+                ("refresh_token", refresh_token),
+                ("client_id", client_id),
+            ]),
+        ProviderType::Gitlab => client.post(format!("{}/oauth/token", provider.url)).form(&[
+            ("grant_type", "refresh_token"),
+            ("refresh_token", refresh_token),
+            ("client_id", client_id),
+        ]),
+        ProviderType::Bitbucket => client
+            .post(bitbucket_token_url(&provider.url))
+            .basic_auth(client_id, Some(bitbucket_secret(provider)?))
+            .form(&[
+                ("grant_type", "refresh_token"),
+                ("refresh_token", refresh_token),
+            ]),
+    };
+
+    let response = request
+        .header("Accept", "application/json")
+        .send()
+        .await
+        .context("Failed to refresh token")?;
+    let token: TokenResponse = response
+        .json()
+        .await
+        .context("Failed to parse token response")?;
+    token.into_credential(Some(refresh_token))
+}
+
+/// OAuth 2.0 device authorization grant (RFC 8628)
+async fn device_flow(
+    client: &Client,
+    device_url: &str,
+    token_url: &str,
+    client_id: &str,
+    scope: &str,
+) -> Result<StoredCredential> {
+    let response = client
+        .post(device_url)
+        .header("Accept", "application/json")
+        .form(&[("client_id", client_id), ("scope", scope)])
+        .send()
+        .await
+        .context("Failed to start device authorization")?;
+    if !response.status().is_success() {
+        anyhow::bail!("Device authorization failed ({})", response.status());
+    }
+    let device: DeviceCodeResponse = response
+        .json()
+        .await
+        .context("Failed to parse device authorization response")?;
+
+    println!(
+        "Open {} and enter the code: {}",
+        device
+            .verification_uri_complete
+            .as_deref()
+            .unwrap_or(&device.verification_uri),
+        device.user_code
+    );
+    println!("Waiting for authorization...");
+
+    let mut interval = device.interval.unwrap_or(5);
+    let deadline = now() + device.expires_in;
+
+    while now() < deadline {
+        tokio::time::sleep(Duration::from_secs(interval)).await;
+
+        let response = client
+            .post(token_url)
+            .header("Accept", "application/json")
+            .form(&[
+                ("client_id", client_id),
+                ("device_code", device.device_code.as_str()),
+                ("grant_type", DEVICE_GRANT_TYPE),
+            ])
+            .send()
+            .await
+            .context("Failed to poll for token")?;
+        let token: TokenResponse = response
+            .json()
+            .await
+            .context("Failed to parse token response")?;
+
+        match token.error.as_deref() {
+            Some("authorization_pending") => continue,
+            Some("slow_down") => interval += 5,
+            _ => return token.into_credential(None),
+        }
+    }
+
+    anyhow::bail!("Device code expired before authorization completed")
+}
+
+/// Web host for a GitHub API URL (api.github.com or <host>/api/v3)
+fn github_web_url(api_url: &str) -> String {
+    let api_url = api_url.trim_end_matches('/');
+    if api_url == "https://api.github.com" {
+        return "https://github.com".to_string();
+    }
+    api_url.trim_end_matches("/api/v3").to_string()
+}
+
+fn bitbucket_token_url(api_url: &str) -> String {
+    let host = api_url
+        .trim_end_matches('/')
+        .trim_end_matches("/2.0")
+        .replace("://api.", "://");
+    format!("{}/site/oauth2/access_token", host)
+}
+
+fn bitbucket_secret(provider: &ResolvedProvider) -> Result<&str> {
+    provider.oauth.client_secret.as_deref().ok_or_else(|| {
+        anyhow::anyhow!(
+            "Bitbucket OAuth consumers need a secret; set providers.{}.client_secret",
+            provider.name
+        )
+    })
+}
@@ -1,4 +1,11 @@
-use clap::Parser;
+use std::ffi::OsString;
+use std::path::PathBuf;
+
+use clap::{CommandFactory, Parser, Subcommand};
+use clap_complete::Shell;
+
+use crate::providers::Affiliation;
+use crate::query::{SearchField, SortKey, SortOrder};
 
 #[derive(Parser, Debug)]
 #[command(name = "repo_search")]
@@ -8,10 +15,13 @@ use clap::Parser;
 )]
 pub struct Args {
     /// Search query (required unless using --list-providers)
+    ///
+    /// A command name on its own, such as `auth`, is searched for; so is
+    /// anything after `--` (`repo_search -- auth`)
     pub query: Option<String>,
 
     /// Provider(s) to search (can specify multiple: -p github -p work-gitlab)
-    /// Use "all" to search all configured providers
+    /// Use "all" to search all configured providers, or a group name from the config
     #[arg(short, long)]
     pub provider: Vec<String>,
 
@@ -19,18 +29,77 @@ pub struct Args {
     #[arg(short = 'u', long)]
     pub url: Option<String>,
 
-    /// Only show repositories you own
-    #[arg(short, long)]
-    pub mine: bool,
+    /// Send provider tokens to a --url host other than the configured one
+    #[arg(long, requires = "url")]
+    pub trust_url: bool,
+
+    /// Only show your repositories, optionally by affiliation (default: owned)
+    #[arg(
+        short,
+        long,
+        value_name = "MODE",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "owned"
+    )]
+    pub mine: Option<Affiliation>,
 
     /// Only show repositories owned by this user/org
     #[arg(long)]
     pub owner: Option<String>,
 
+    /// Fields to match the query text against
+    #[arg(long = "in", value_delimiter = ',', value_name = "FIELDS")]
+    pub fields: Vec<SearchField>,
+
+    /// Only repositories in this language (same as `lang:`)
+    #[arg(long)]
+    pub language: Option<String>,
+
+    /// Only repositories with this topic (can specify multiple)
+    #[arg(long)]
+    pub topic: Vec<String>,
+
+    /// Only repositories with at least this many stars
+    #[arg(long, value_name = "N")]
+    pub min_stars: Option<u64>,
+
+    /// Only repositories updated on or after this date (YYYY-MM-DD)
+    #[arg(long, value_name = "DATE")]
+    pub updated_since: Option<String>,
+
+    /// Only archived repositories
+    #[arg(long, overrides_with = "no_archived")]
+    pub archived: bool,
+
+    /// Leave out archived repositories
+    #[arg(long)]
+    pub no_archived: bool,
+
+    /// Only forks
+    #[arg(long, overrides_with = "no_forks")]
+    pub forks: bool,
+
+    /// Leave out forks
+    #[arg(long)]
+    pub no_forks: bool,
+
+    /// Order results by relevance (default), stars, updated, name or created
+    #[arg(long, value_name = "KEY")]
+    pub sort: Option<SortKey>,
+
+    /// Sort direction (default: asc for name, desc otherwise)
+    #[arg(long, requires = "sort")]
+    pub order: Option<SortOrder>,
+
     /// Maximum results per provider (default: 10, or from config)
     #[arg(short, long)]
     pub limit: Option<usize>,
 
+    /// Maximum results overall, shared fairly between providers (default: no limit, or from config)
+    #[arg(long, value_name = "N")]
+    pub total_limit: Option<usize>,
+
     /// Output as JSON
     #[arg(long)]
     pub json: bool,
@@ -38,8 +107,130 @@ pub struct Args {
     /// List all configured providers and exit
     #[arg(long)]
     pub list_providers: bool,
+
+    /// Extra config file merged on top of the system, user and project files
+    #[arg(long, global = true, env = "REPO_SEARCH_CONFIG")]
+    pub config: Option<PathBuf>,
+
+    /// Named profile from the config to apply (e.g. work, personal)
+    #[arg(long, global = true, env = "REPO_SEARCH_PROFILE")]
+    pub profile: Option<String>,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Inspect and validate the configuration
+    Config {
+        #[command(subcommand)]
+        action: ConfigCommand,
+    },
+    /// Log in to providers and manage stored credentials
+    Auth {
+        #[command(subcommand)]
+        action: AuthCommand,
+    },
+    /// Print a shell completion script, including configured providers and groups
+    Completions {
+        /// Shell to generate completions for
+        shell: Shell,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum AuthCommand {
+    /// Log in with OAuth and store the token in the credentials file
+    Login {
+        /// Provider name (e.g. github, work-gitlab)
+        provider: String,
+    },
+    /// Remove a provider's stored token
+    Logout {
+        /// Provider name (e.g. github, work-gitlab)
+        provider: String,
+    },
+    /// Show who each token belongs to, its scopes, expiry and rate limit
+    Status {
+        /// Providers or groups to check (default: all configured providers)
+        providers: Vec<String>,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ConfigCommand {
+    /// Validate every provider (type, URL, token) without searching
+    Check,
+    /// Interactively create a config file
+    Init,
+    /// Show the effective config and where each value came from
+    Show,
+    /// Set a value in the config file (e.g. providers.work-gitlab.url)
+    Set {
+        /// Dotted key to set
+        key: String,
+        /// Value to store (parsed as TOML, falling back to a string)
+        value: String,
+    },
+    /// Print the config file that init and set write to
+    Path,
+    /// Print a JSON Schema for the config file (for editor validation)
+    Schema,
+    /// Move legacy [github]/[gitlab]/[bitbucket] sections under [providers]
+    Migrate {
+        /// Show the changes as a diff without writing the file
+        #[arg(long)]
+        dry_run: bool,
+    },
 }
 
 pub fn parse() -> Args {
-    Args::parse()
+    parse_from(std::env::args_os()).unwrap_or_else(|err| err.exit())
+}
+
+/// Parse `argv`, searching for a command name that can't run as a command,
+/// such as a bare `repo_search auth`, as searches did before the commands
+/// existed
+pub fn parse_from<I, T>(argv: I) -> Result<Args, clap::Error>
+where
+    I: IntoIterator<Item = T>,
+    T: Into<OsString> + Clone,
+{
+    let argv: Vec<OsString> = argv.into_iter().map(Into::into).collect();
+    let err = match Args::try_parse_from(&argv) {
+        Ok(args) => return Ok(args),
+        Err(err) => err,
+    };
+
+    let command = Args::command();
+    let is_command = |arg: &OsString| {
+        command
+            .get_subcommands()
+            .any(|subcommand| arg == subcommand.get_name())
+    };
+    let Some(index) = argv
+        .iter()
+        .take_while(|arg| *arg != "--")
+        .position(is_command)
+        .filter(|&index| index > 0)
+    else {
+        return Err(err);
+    };
+    let mut retry = argv;
+    let word = retry.remove(index);
+    retry.push("--".into());
+    retry.push(word.clone());
+    match Args::try_parse_from(retry) {
+        Ok(args) if args.query.as_deref().map(OsString::from) == Some(word) => Ok(args),
+        _ => Err(err),
+    }
+}
+
+/// Build the clap command with `names` offered as `--provider` completions
+pub fn command_with_providers(names: Vec<String>) -> clap::Command {
+    Args::command().mut_arg("provider", |arg| {
+        arg.value_parser(clap::builder::PossibleValuesParser::new(names))
+            .hide_possible_values(true)
+    })
 }
@@ -0,0 +1,148 @@
+use anyhow::Result;
+
+use crate::auth::credentials::format_timestamp;
+use crate::auth::{self, github_app, oauth};
+use crate::cli::AuthCommand;
+use crate::config::{Config, ResolvedProvider};
+use crate::providers;
+use crate::security::redact;
+
+pub async fn run(action: &AuthCommand, config: &mut Config) -> Result<()> {
+    match action {
+        AuthCommand::Login { provider } => login(provider, config).await,
+        AuthCommand::Logout { provider } => logout(provider, config),
+        AuthCommand::Status { providers } => status(providers, config).await,
+    }
+}
+
+async fn login(name: &str, config: &Config) -> Result<()> {
+    let provider = config
+        .resolve_provider(name)
+        .ok_or_else(|| anyhow::anyhow!("Unknown provider '{}'", name))?;
+
+    let credential = oauth::login(&provider).await?;
+
+    let mut store = config.credential_store()?;
+    store.set(&config.credential_key(name), credential);
+    store.save()?;
+
+    match store.path() {
+        Some(path) => println!("Logged in to {}; token stored in {}", name, path.display()),
+        None => println!("Logged in to {}", name),
+    }
+    Ok(())
+}
+
+fn logout(name: &str, config: &Config) -> Result<()> {
+    let mut store = config.credential_store()?;
+    if store.remove(&config.credential_key(name)) {
+        store.save()?;
+        println!("Removed stored token for {}", name);
+    } else {
+        println!("No stored token for {}", name);
+    }
+    Ok(())
+}
+
+async fn status(names: &[String], config: &mut Config) -> Result<()> {
+    let names = if names.is_empty() {
+        config.provider_names()
+    } else {
+        config.expand_provider_names(names)
+    };
+    for error in providers::detect::detect_types(config, &names).await {
+        eprintln!("Warning: {}", error);
+    }
+    let config = &*config;
+
+    let mut resolved: Vec<ResolvedProvider> = Vec::new();
+    let mut failed = false;
+    for name in &names {
+        match config.resolve_provider(name) {
+            Some(provider) => resolved.push(provider),
+            None => {
+                eprintln!("Error: Unknown provider '{}'", name);
+                failed = true;
+            }
+        }
+    }
+
+    for warning in auth::refresh_expired(&mut resolved, config).await {
+        eprintln!("Warning: {}", warning);
+    }
+    for error in auth::apply_github_apps(&mut resolved, None).await {
+        eprintln!("Error: {}", error);
+        failed = true;
+    }
+
+    for provider in &resolved {
+        println!(
+            "{} [{}] -> {}",
+            provider.name,
+            provider.provider_type.as_str(),
+            provider.url
+        );
+        if provider.token.is_none() {
+            println!("  not authenticated");
+            continue;
+        }
+
+        if let Some(app) = &provider.github_app {
+            match github_app::status(provider, app).await {
+                Ok(status) => {
+                    println!("  app: {} (id {})", status.slug, app.app_id);
+                    match &status.account {
+                        Some(account) => {
+                            println!("  installation: {} on {}", status.installation_id, account)
+                        }
+                        None => println!("  installation: {}", status.installation_id),
+                    }
+                    if let Some(remaining) = status.rate_limit_remaining {
+                        println!("  rate limit remaining: {}", remaining);
+                    }
+                }
+                Err(e) => {
+                    println!("  error: {}", redact(&e.to_string(), &provider.secrets()));
+                    failed = true;
+                }
+            }
+            continue;
+        }
+
+        let info = match providers::build(provider).token_info().await {
+            Ok(info) => info,
+            Err(e) => {
+                println!("  error: {}", redact(&e.to_string(), &provider.secrets()));
+                failed = true;
+                continue;
+            }
+        };
+
+        println!("  user: {}", info.username);
+        match &info.scopes {
+            Some(scopes) if scopes.is_empty() => println!("  scopes: (none)"),
+            Some(scopes) => println!("  scopes: {}", scopes.join(", ")),
+            None => println!("  scopes: (not reported for this token)"),
+        }
+        // Prefer what the provider reports; fall back to the stored OAuth expiry
+        let expires = info.expires_at.clone().or_else(|| {
+            provider
+                .credential
+                .as_ref()
+                .and_then(|credential| credential.expires_at)
+                .map(format_timestamp)
+        });
+        println!("  expires: {}", expires.as_deref().unwrap_or("never"));
+        if let Some(remaining) = info.rate_limit_remaining {
+            println!("  rate limit remaining: {}", remaining);
+        }
+        for warning in &info.warnings {
+            println!("  warning: {}", warning);
+        }
+    }
+
+    if failed {
+        std::process::exit(1);
+    }
+    Ok(())
+}
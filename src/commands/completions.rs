@@ -0,0 +1,15 @@
+use std::io;
+
+use clap_complete::Shell;
+
+use crate::cli;
+use crate::config::Config;
+
+pub fn run(shell: Shell, config: &Config) {
+    let mut names = vec!["all".to_string()];
+    names.extend(config.provider_names());
+    names.extend(config.group_names());
+
+    let mut command = cli::command_with_providers(names);
+    clap_complete::generate(shell, &mut command, "repo_search", &mut io::stdout());
+}
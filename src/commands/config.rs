@@ -0,0 +1,611 @@
+use std::fs;
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use similar::TextDiff;
+use toml_edit::{value, Array, DocumentMut, Item, Table, Value};
+
+use crate::auth::credentials::StoredCredential;
+use crate::cli::ConfigCommand;
+use crate::config::{Config, LoadOptions, ProviderType, Severity, DEFAULT_LIMIT};
+use crate::providers::detect;
+use crate::security::REDACTED;
+
+pub async fn run(action: &ConfigCommand, options: &LoadOptions) -> Result<()> {
+    match action {
+        ConfigCommand::Check => check(options).await,
+        ConfigCommand::Init => init(&target_path(options)?),
+        ConfigCommand::Show => show(options),
+        ConfigCommand::Set { key, value } => set(options, key, value),
+        ConfigCommand::Schema => {
+            print!("{}", Config::json_schema());
+            Ok(())
+        }
+        ConfigCommand::Migrate { dry_run } => migrate(&target_path(options)?, *dry_run),
+        ConfigCommand::Path => {
+            println!("{}", target_path(options)?.display());
+            Ok(())
+        }
+    }
+}
+
+/// File edited by `init`, `set` and `migrate`: the explicit `--config` file, else the user file
+fn target_path(options: &LoadOptions) -> Result<PathBuf> {
+    match &options.config_file {
+        Some(path) => Ok(path.clone()),
+        None => Config::config_path(),
+    }
+}
+
+fn print_files(config: &Config) {
+    if config.files().is_empty() {
+        println!("# No config files found");
+    }
+    for file in config.files() {
+        println!("# Loaded {}", file.display());
+    }
+    if let Some(profile) = config.active_profile() {
+        println!("# Profile {}", profile);
+    }
+}
+
+async fn check(options: &LoadOptions) -> Result<()> {
+    let mut config = Config::load(options)?;
+    print_files(&config);
+
+    let names = config.provider_names();
+    for error in detect::detect_types(&mut config, &names).await {
+        println!("# Detection failed for {}", error);
+    }
+
+    let checks = config.check();
+    let mut failed = false;
+
+    for check in &checks {
+        let type_str = check.provider_type.map_or("unknown", |t| t.as_str());
+        let status = if check.has_errors() {
+            failed = true;
+            "error"
+        } else if check.issues.is_empty() {
+            "ok"
+        } else {
+            "warning"
+        };
+        let url = check.url.as_deref().unwrap_or("-");
+        println!("  {} [{}] -> {}: {}", check.name, type_str, url, status);
+
+        for issue in &check.issues {
+            let label = match issue.severity {
+                Severity::Warning => "warning",
+                Severity::Error => "error",
+            };
+            println!("      {}: {}", label, issue.message);
+        }
+    }
+
+    if failed {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+fn show(options: &LoadOptions) -> Result<()> {
+    let config = Config::load(options)?;
+    print_files(&config);
+    println!();
+
+    let providers = config.default_providers();
+    let limit = config.defaults.limit.unwrap_or(DEFAULT_LIMIT);
+    println!("[defaults]");
+    print_value(
+        "providers",
+        &format!("{:?}", providers),
+        &config,
+        "defaults.providers",
+    );
+    print_value("limit", &limit.to_string(), &config, "defaults.limit");
+    if let Some(total_limit) = config.defaults.total_limit {
+        print_value(
+            "total_limit",
+            &total_limit.to_string(),
+            &config,
+            "defaults.total_limit",
+        );
+    }
+
+    if let Some(encryption) = config.credentials.encryption {
+        println!();
+        println!("[credentials]");
+        print_value(
+            "encryption",
+            &format!("{:?}", encryption.as_str()),
+            &config,
+            "credentials.encryption",
+        );
+        if let Some(identity) = &config.credentials.identity {
+            print_value(
+                "identity",
+                &format!("{:?}", identity.display().to_string()),
+                &config,
+                "credentials.identity",
+            );
+        }
+    }
+
+    let security = &config.security;
+    if security.strict.is_some() || security.trusted_hosts.is_some() {
+        println!();
+        println!("[security]");
+        if let Some(strict) = security.strict {
+            print_value("strict", &strict.to_string(), &config, "security.strict");
+        }
+        if let Some(hosts) = &security.trusted_hosts {
+            print_value(
+                "trusted_hosts",
+                &format!("{:?}", hosts),
+                &config,
+                "security.trusted_hosts",
+            );
+        }
+    }
+
+    for name in config.provider_names() {
+        println!();
+        println!("[providers.{}]", name);
+        let Some(resolved) = config.resolve_provider(&name) else {
+            println!("# unresolved: type cannot be inferred from the name");
+            continue;
+        };
+
+        let prefix = format!("providers.{}", name);
+        print_value(
+            "type",
+            &format!("{:?}", resolved.provider_type.as_str()),
+            &config,
+            &format!("{}.type", prefix),
+        );
+        print_value(
+            "url",
+            &format!("{:?}", resolved.url),
+            &config,
+            &format!("{}.url", prefix),
+        );
+        if resolved.token.is_some() {
+            print_value(
+                "token",
+                &format!("{:?}", REDACTED),
+                &config,
+                &format!("{}.token", prefix),
+            );
+        }
+
+        let scope = &resolved.scope;
+        let scope_values = [
+            ("owner", scope.owner.as_ref().map(|v| format!("{:?}", v))),
+            (
+                "include_archived",
+                scope.include_archived.map(|v| v.to_string()),
+            ),
+            (
+                "extra_query",
+                scope.extra_query.as_ref().map(|v| format!("{:?}", v)),
+            ),
+            ("limit", scope.limit.map(|v| v.to_string())),
+            ("weight", scope.weight.map(|v| v.to_string())),
+        ];
+        for (field, value) in scope_values {
+            if let Some(value) = value {
+                print_value(field, &value, &config, &format!("{}.{}", prefix, field));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn print_value(field: &str, value: &str, config: &Config, key: &str) {
+    println!("{} = {}  # {}", field, value, config.source_of(key));
+}
+
+fn set(options: &LoadOptions, key: &str, raw_value: &str) -> Result<()> {
+    let path = &target_path(options)?;
+
+    // With an encrypted credentials file, tokens go there instead of the config
+    let token_provider = key
+        .strip_prefix("providers.")
+        .and_then(|rest| rest.strip_suffix(".token"))
+        .filter(|name| !name.contains('.'));
+    if let Some(name) = token_provider {
+        let config = Config::load(options)?;
+        let mut store = config.credential_store()?;
+        if store.is_encrypted() {
+            store.set(
+                &config.credential_key(name),
+                StoredCredential {
+                    access_token: raw_value.to_string(),
+                    refresh_token: None,
+                    expires_at: None,
+                },
+            );
+            store.save()?;
+
+            if path.exists() {
+                let content = fs::read_to_string(path)
+                    .with_context(|| format!("Failed to read {}", path.display()))?;
+                if let Some(updated) = remove_value(&content, key)? {
+                    Config::write_file(path, &updated)?;
+                    println!("Removed plaintext {} from {}", key, path.display());
+                }
+            }
+            if let Some(store_path) = store.path() {
+                println!("Stored {} in {}", key, store_path.display());
+            }
+            return Ok(());
+        }
+    }
+
+    let content = if path.exists() {
+        fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?
+    } else {
+        String::new()
+    };
+
+    let updated = set_value(&content, key, raw_value)?;
+    Config::write_file(path, &updated)?;
+
+    println!("Set {} in {}", key, path.display());
+    Ok(())
+}
+
+/// Set a dotted `key` in a TOML document, keeping the rest of the file intact
+fn set_value(content: &str, key: &str, raw_value: &str) -> Result<String> {
+    let mut doc: DocumentMut = content.parse().context("Failed to parse config file")?;
+
+    let parts: Vec<&str> = key.split('.').collect();
+    if parts.iter().any(|part| part.is_empty()) {
+        anyhow::bail!("Invalid key '{}'", key);
+    }
+    let (field, tables) = parts
+        .split_last()
+        .expect("split produces at least one part");
+
+    let mut table = doc.as_table_mut();
+    for part in tables {
+        let item = table.entry(part).or_insert_with(|| {
+            let mut new_table = Table::new();
+            new_table.set_implicit(true);
+            Item::Table(new_table)
+        });
+        table = item
+            .as_table_mut()
+            .ok_or_else(|| anyhow::anyhow!("'{}' in '{}' is not a table", part, key))?;
+    }
+
+    // Accept TOML literals (numbers, arrays, booleans) and fall back to a plain string
+    let parsed = raw_value
+        .parse::<Value>()
+        .unwrap_or_else(|_| Value::from(raw_value));
+    table.insert(field, value(parsed));
+
+    Ok(doc.to_string())
+}
+
+fn migrate(path: &Path, dry_run: bool) -> Result<()> {
+    let content =
+        fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let (updated, notes) = migrate_legacy(&content)?;
+
+    for note in &notes {
+        eprintln!("Note: {}", note);
+    }
+    if updated == content {
+        if notes.is_empty() {
+            println!("{} has no legacy sections", path.display());
+        } else {
+            println!("Nothing to migrate automatically in {}", path.display());
+        }
+        return Ok(());
+    }
+
+    if dry_run {
+        let name = path.display().to_string();
+        print!(
+            "{}",
+            TextDiff::from_lines(&content, &updated)
+                .unified_diff()
+                .header(&name, &name)
+        );
+        return Ok(());
+    }
+
+    Config::write_file(path, &updated)?;
+    println!("Migrated legacy sections in {}", path.display());
+    Ok(())
+}
+
+/// Move top-level `[github]`/`[gitlab]`/`[bitbucket]` tables to
+/// `[providers.*]`, keeping comments and layout. Returns the new content and
+/// notes about sections that could not be moved as they were.
+fn migrate_legacy(content: &str) -> Result<(String, Vec<String>)> {
+    let mut doc: DocumentMut = content.parse().context("Failed to parse config file")?;
+    let mut notes = Vec::new();
+
+    for name in ["github", "gitlab", "bitbucket"] {
+        if !doc.contains_key(name) {
+            continue;
+        }
+        // Matches loading: an existing [providers.<name>] always wins. Keep the
+        // legacy section rather than lose its values; the user has to merge them.
+        let conflict = doc
+            .get("providers")
+            .and_then(Item::as_table_like)
+            .is_some_and(|providers| providers.contains_key(name));
+        if conflict {
+            notes.push(format!(
+                "left [{}] in place; it is ignored because [providers.{}] exists, so move any values you need by hand",
+                name, name
+            ));
+            continue;
+        }
+        let Some(legacy) = doc.remove(name) else {
+            continue;
+        };
+
+        let providers = doc.entry("providers").or_insert_with(|| {
+            let mut table = Table::new();
+            table.set_implicit(true);
+            Item::Table(table)
+        });
+        let providers = providers
+            .as_table_mut()
+            .ok_or_else(|| anyhow::anyhow!("'providers' is not a table"))?;
+        providers.insert(name, legacy);
+    }
+
+    Ok((doc.to_string(), notes))
+}
+
+/// Remove a dotted `key` from a TOML document, or `None` if it isn't set
+fn remove_value(content: &str, key: &str) -> Result<Option<String>> {
+    let mut doc: DocumentMut = content.parse().context("Failed to parse config file")?;
+
+    let parts: Vec<&str> = key.split('.').collect();
+    let (field, tables) = parts
+        .split_last()
+        .expect("split produces at least one part");
+
+    let mut table = doc.as_table_mut();
+    for part in tables {
+        match table.get_mut(part).and_then(Item::as_table_mut) {
+            Some(inner) => table = inner,
+            None => return Ok(None),
+        }
+    }
+
+    Ok(table.remove(field).map(|_| doc.to_string()))
+}
+
+fn init(path: &Path) -> Result<()> {
+    let stdin = io::stdin();
+    let mut input = stdin.lock();
+
+    println!("This will create {}", path.display());
+    if path.exists() && !confirm(&mut input, "A config file already exists. Overwrite it?")? {
+        println!("Aborted.");
+        return Ok(());
+    }
+
+    let mut doc = DocumentMut::new();
+    let mut providers = Table::new();
+    providers.set_implicit(true);
+    let mut configured = Vec::new();
+
+    for provider_type in [
+        ProviderType::Github,
+        ProviderType::Gitlab,
+        ProviderType::Bitbucket,
+    ] {
+        let name = provider_type.as_str();
+        if !confirm(&mut input, &format!("Configure {}?", name))? {
+            continue;
+        }
+        let entry = prompt_provider(&mut input, name, provider_type, false)?;
+        providers.insert(name, Item::Table(entry));
+        configured.push(name.to_string());
+    }
+
+    while confirm(
+        &mut input,
+        "Add a custom provider instance (e.g. work-gitlab)?",
+    )? {
+        let name = prompt(&mut input, "Name", None)?;
+        if name.is_empty() {
+            continue;
+        }
+        let provider_type = loop {
+            let answer = prompt(&mut input, "Type (github, gitlab, bitbucket)", None)?;
+            match ProviderType::from_name(&answer) {
+                Some(provider_type) => break provider_type,
+                None => println!("Unknown type '{}'", answer),
+            }
+        };
+        let entry = prompt_provider(&mut input, &name, provider_type, true)?;
+        providers.insert(&name, Item::Table(entry));
+        configured.push(name);
+    }
+
+    let mut defaults = Table::new();
+    let suggested = if configured.is_empty() {
+        "github, gitlab, bitbucket".to_string()
+    } else {
+        configured.join(", ")
+    };
+    let answer = prompt(&mut input, "Default providers to search", Some(&suggested))?;
+    let default_providers: Array = answer
+        .split(',')
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .collect();
+    defaults.insert("providers", value(default_providers));
+
+    let limit_default = DEFAULT_LIMIT.to_string();
+    let limit = prompt(&mut input, "Results per provider", Some(&limit_default))?;
+    let limit: i64 = limit
+        .parse()
+        .with_context(|| format!("Invalid limit '{}'", limit))?;
+    defaults.insert("limit", value(limit));
+
+    doc.insert("defaults", Item::Table(defaults));
+    doc.insert("providers", Item::Table(providers));
+
+    Config::write_file(path, &doc.to_string())?;
+    println!("Wrote {}", path.display());
+    Ok(())
+}
+
+fn prompt_provider(
+    input: &mut impl BufRead,
+    name: &str,
+    provider_type: ProviderType,
+    custom: bool,
+) -> Result<Table> {
+    let mut entry = Table::new();
+    if custom {
+        entry.insert("type", value(provider_type.as_str()));
+    }
+
+    let url = prompt(
+        input,
+        &format!("{} URL", name),
+        Some(provider_type.default_url()),
+    )?;
+    if custom || url != provider_type.default_url() {
+        entry.insert("url", value(url));
+    }
+
+    let token = rpassword::prompt_password(format!("{} token (leave empty for none): ", name))
+        .context("Failed to read token")?;
+    if !token.trim().is_empty() {
+        entry.insert("token", value(token.trim()));
+    }
+
+    Ok(entry)
+}
+
+fn prompt(input: &mut impl BufRead, question: &str, default: Option<&str>) -> Result<String> {
+    match default {
+        Some(default) => print!("{} [{}]: ", question, default),
+        None => print!("{}: ", question),
+    }
+    io::stdout().flush()?;
+
+    let mut line = String::new();
+    input.read_line(&mut line)?;
+    let answer = line.trim();
+
+    Ok(match default {
+        Some(default) if answer.is_empty() => default.to_string(),
+        _ => answer.to_string(),
+    })
+}
+
+fn confirm(input: &mut impl BufRead, question: &str) -> Result<bool> {
+    let answer = prompt(input, &format!("{} [y/N]", question), None)?;
+    Ok(matches!(answer.to_lowercase().as_str(), "y" | "yes"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_set_value_preserves_comments() {
+        let content = "# my config\n[defaults]\nlimit = 5 # keep\n";
+        let updated = set_value(
+            content,
+            "providers.work-gitlab.url",
+            "https://gitlab.work.com",
+        )
+        .unwrap();
+
+        assert!(updated.starts_with("# my config\n[defaults]\nlimit = 5 # keep\n"));
+        assert!(updated.contains("[providers.work-gitlab]\nurl = \"https://gitlab.work.com\"\n"));
+    }
+
+    #[test]
+    fn test_set_value_parses_toml_literals() {
+        let updated = set_value("", "defaults.limit", "20").unwrap();
+        assert!(updated.contains("limit = 20"));
+
+        let updated = set_value(&updated, "defaults.providers", r#"["github", "gitlab"]"#).unwrap();
+        let config = Config::from_toml(&updated).unwrap();
+        assert_eq!(config.defaults.limit, Some(20));
+        assert_eq!(
+            config.defaults.providers,
+            Some(vec!["github".to_string(), "gitlab".to_string()])
+        );
+    }
+
+    #[test]
+    fn test_migrate_legacy_keeps_comments() {
+        let content = "\
+[defaults]
+limit = 5
+
+# Personal account
+[github]
+token = \"ghp_x\" # rotate yearly
+
+[gitlab]
+url = \"https://gitlab.example.com\"
+
+[providers.gitlab]
+type = \"gitlab\"
+";
+        let (updated, notes) = migrate_legacy(content).unwrap();
+        assert_eq!(
+            updated,
+            "\
+[defaults]
+limit = 5
+
+# Personal account
+[providers.github]
+token = \"ghp_x\" # rotate yearly
+
+[gitlab]
+url = \"https://gitlab.example.com\"
+
+[providers.gitlab]
+type = \"gitlab\"
+"
+        );
+        // The conflicting section is kept and reported, not dropped
+        assert_eq!(notes.len(), 1);
+        assert!(notes[0].contains("[gitlab]"));
+
+        let (unchanged, notes) = migrate_legacy(&updated).unwrap();
+        assert_eq!(unchanged, updated);
+        assert_eq!(notes.len(), 1);
+    }
+
+    #[test]
+    fn test_remove_value() {
+        let content = "# Work\n[providers.work]\ntype = \"gitlab\"\ntoken = \"secret\"\n";
+        let updated = remove_value(content, "providers.work.token")
+            .unwrap()
+            .unwrap();
+        assert_eq!(updated, "# Work\n[providers.work]\ntype = \"gitlab\"\n");
+        assert!(remove_value(content, "providers.other.token")
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn test_set_value_rejects_non_table_parent() {
+        let content = "[defaults]\nlimit = 5\n";
+        assert!(set_value(content, "defaults.limit.value", "1").is_err());
+        assert!(set_value(content, "defaults..limit", "1").is_err());
+    }
+}
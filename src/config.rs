@@ -1,11 +1,95 @@
 use anyhow::Result;
-use serde::Deserialize;
-use std::collections::HashMap;
+use reqwest::Url;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
 use std::env;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use thiserror::Error;
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+use crate::auth::credentials::{CredentialStore, StoredCredential};
+use crate::providers::detect::{Detected, DetectionCache};
+use crate::security;
+
+/// Result limit per provider when neither the CLI nor the config sets one
+pub const DEFAULT_LIMIT: usize = 10;
+
+/// Project-local config file, discovered by walking up from the CWD
+pub const PROJECT_CONFIG_FILE: &str = ".repo_search.toml";
+
+/// System-wide config file, merged before the user file
+#[cfg(unix)]
+const SYSTEM_CONFIG_PATH: Option<&str> = Some("/etc/repo_search/config.toml");
+#[cfg(not(unix))]
+const SYSTEM_CONFIG_PATH: Option<&str> = None;
+
+/// Errors raised while reading or parsing the config file
+#[derive(Debug, Error)]
+pub enum ConfigError {
+    #[error("Failed to read config file {}: {source}", path.display())]
+    Read {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[error("Config file not found: {}", path.display())]
+    NotFound { path: PathBuf },
+    #[error(
+        "Unknown profile '{name}'{}",
+        suggestion.as_ref().map(|s| format!("; did you mean '{}'?", s)).unwrap_or_default()
+    )]
+    UnknownProfile {
+        name: String,
+        suggestion: Option<String>,
+    },
+    #[error("Config include cycle: {} includes itself", path.display())]
+    IncludeCycle { path: PathBuf },
+    #[error(
+        "Config file {} contains tokens but is readable by other users (mode {mode:o}); run `chmod 600 {}`",
+        path.display(),
+        path.display()
+    )]
+    InsecurePermissions { path: PathBuf, mode: u32 },
+    #[error(
+        "{} changes the URL of provider '{name}', whose token is configured elsewhere; set `token` in the same file or remove `url`",
+        path.display()
+    )]
+    UntrustedUrl { path: PathBuf, name: String },
+    #[error(
+        "Invalid config file {}:{line}:{column}: {message}{}{}",
+        path.display(),
+        key.as_ref().map(|key| format!(" (at `{}`)", key)).unwrap_or_default(),
+        suggestion.as_ref().map(|s| format!("; did you mean `{}`?", s)).unwrap_or_default()
+    )]
+    Parse {
+        path: PathBuf,
+        line: usize,
+        column: usize,
+        key: Option<String>,
+        message: String,
+        suggestion: Option<String>,
+    },
+}
+
+impl ConfigError {
+    fn from_toml(path: &Path, content: &str, err: toml::de::Error) -> Self {
+        let offset = err.span().map(|span| span.start).unwrap_or(0);
+        let (line, column) = line_column(content, offset);
+        let message = err.message().replace('\n', "; ");
+
+        Self::Parse {
+            path: path.to_path_buf(),
+            line,
+            column,
+            key: key_at_line(content, line),
+            suggestion: suggest_field(&message),
+            message,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, JsonSchema)]
 #[serde(rename_all = "lowercase")]
 pub enum ProviderType {
     Github,
@@ -14,6 +98,14 @@ pub enum ProviderType {
 }
 
 impl ProviderType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ProviderType::Github => "github",
+            ProviderType::Gitlab => "gitlab",
+            ProviderType::Bitbucket => "bitbucket",
+        }
+    }
+
     pub fn default_url(&self) -> &'static str {
         match self {
             ProviderType::Github => "https://api.github.com",
@@ -33,8 +125,15 @@ impl ProviderType {
     }
 }
 
-#[derive(Debug, Clone, Default, Deserialize)]
+/// repo_search configuration (`config.toml`)
+#[derive(Debug, Clone, Default, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
 pub struct Config {
+    /// Other config files to merge before this one, relative to this file
+    #[serde(default)]
+    include: Vec<String>,
+
+    /// Defaults used when the command line does not say otherwise
     #[serde(default)]
     pub defaults: DefaultsConfig,
 
@@ -43,33 +142,244 @@ pub struct Config {
     pub providers: HashMap<String, ProviderEntry>,
 
     // Legacy top-level provider configs (backwards compatibility)
+    /// Legacy GitHub section; prefer `[providers.github]`
     #[serde(default)]
     github: Option<LegacyProviderConfig>,
+    /// Legacy GitLab section; prefer `[providers.gitlab]`
     #[serde(default)]
     gitlab: Option<LegacyProviderConfig>,
+    /// Legacy Bitbucket section; prefer `[providers.bitbucket]`
     #[serde(default)]
     bitbucket: Option<LegacyProviderConfig>,
+
+    /// Named groups of providers, usable anywhere a provider name is
+    #[serde(default)]
+    pub groups: HashMap<String, Vec<String>>,
+
+    /// Named profiles, each with its own defaults and providers
+    #[serde(default)]
+    pub profiles: HashMap<String, ProfileConfig>,
+
+    /// How tokens from `auth login` are stored
+    #[serde(default)]
+    pub credentials: CredentialsConfig,
+
+    /// Safeguards for files and hosts that see tokens
+    #[serde(default)]
+    pub security: SecurityConfig,
+
+    /// Profile applied by `Config::load`, if any
+    #[serde(skip)]
+    active_profile: Option<String>,
+
+    /// Where each effective value came from, keyed by dotted path
+    #[serde(skip)]
+    sources: BTreeMap<String, ValueSource>,
+
+    /// Config files that were merged, in load order
+    #[serde(skip)]
+    files: Vec<PathBuf>,
+
+    /// Merged files holding secrets that other users can read, with their mode
+    #[serde(skip)]
+    insecure_files: Vec<(PathBuf, u32)>,
+
+    /// Provider URLs set by project-local or included files without a token,
+    /// keyed like `sources` (`providers.<name>`), with the file that set them
+    #[serde(skip)]
+    untrusted_urls: BTreeMap<String, PathBuf>,
+
+    /// Tokens stored by `auth login`, read (and decrypted) on first use
+    #[serde(skip)]
+    store: OnceLock<Result<CredentialStore, String>>,
+}
+
+/// Options controlling which config files are loaded
+#[derive(Debug, Clone, Default)]
+pub struct LoadOptions {
+    /// Explicit config file (`--config` / `REPO_SEARCH_CONFIG`), merged last
+    pub config_file: Option<PathBuf>,
+    /// Profile to apply (`--profile` / `REPO_SEARCH_PROFILE`)
+    pub profile: Option<String>,
+}
+
+/// Origin of an effective config value
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ValueSource {
+    File(PathBuf),
+    Env(&'static str),
+    Credentials(PathBuf),
+    /// Found by probing the provider's URL
+    Detected,
+    Default,
+}
+
+impl std::fmt::Display for ValueSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ValueSource::File(path) => write!(f, "file {}", path.display()),
+            ValueSource::Env(var) => write!(f, "env {}", var),
+            ValueSource::Credentials(path) => write!(f, "credentials {}", path.display()),
+            ValueSource::Detected => write!(f, "detected"),
+            ValueSource::Default => write!(f, "default"),
+        }
+    }
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Default, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
 pub struct DefaultsConfig {
     /// Default providers to search (e.g., ["github", "gitlab", "work-bb"])
     pub providers: Option<Vec<String>>,
     /// Default result limit per provider
     pub limit: Option<usize>,
+    /// Maximum results overall, shared fairly between providers
+    pub total_limit: Option<usize>,
 }
 
-impl Default for DefaultsConfig {
-    fn default() -> Self {
-        Self {
-            providers: None,
-            limit: None,
+impl DefaultsConfig {
+    fn merge(&mut self, other: DefaultsConfig) {
+        if other.providers.is_some() {
+            self.providers = other.providers;
         }
+        if other.limit.is_some() {
+            self.limit = other.limit;
+        }
+        if other.total_limit.is_some() {
+            self.total_limit = other.total_limit;
+        }
+    }
+
+    /// Names of the fields that are set
+    fn set_fields(&self) -> Vec<&'static str> {
+        let mut fields = Vec::new();
+        if self.providers.is_some() {
+            fields.push("providers");
+        }
+        if self.limit.is_some() {
+            fields.push("limit");
+        }
+        if self.total_limit.is_some() {
+            fields.push("total_limit");
+        }
+        fields
+    }
+}
+
+/// How the credentials file is protected
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum CredentialEncryption {
+    /// Passphrase from `REPO_SEARCH_PASSPHRASE` or the terminal
+    Passphrase,
+    /// age identity file (see `identity`)
+    Age,
+}
+
+impl CredentialEncryption {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CredentialEncryption::Passphrase => "passphrase",
+            CredentialEncryption::Age => "age",
+        }
+    }
+}
+
+/// Settings for the `auth login` credentials file (`[credentials]`)
+#[derive(Debug, Clone, Default, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct CredentialsConfig {
+    /// Encrypt the credentials file instead of storing tokens in plain text
+    pub encryption: Option<CredentialEncryption>,
+    /// age identity file (as written by `age-keygen`) for `encryption = "age"`
+    pub identity: Option<PathBuf>,
+}
+
+impl CredentialsConfig {
+    fn merge(&mut self, other: CredentialsConfig) {
+        if other.encryption.is_some() {
+            self.encryption = other.encryption;
+        }
+        if other.identity.is_some() {
+            self.identity = other.identity;
+        }
+    }
+
+    /// Names of the fields that are set
+    fn set_fields(&self) -> Vec<&'static str> {
+        let mut fields = Vec::new();
+        if self.encryption.is_some() {
+            fields.push("encryption");
+        }
+        if self.identity.is_some() {
+            fields.push("identity");
+        }
+        fields
+    }
+}
+
+/// Token safeguards (`[security]`)
+#[derive(Debug, Clone, Default, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct SecurityConfig {
+    /// Refuse to load config files with tokens that other users can read
+    pub strict: Option<bool>,
+    /// Hosts that `--url` may send a provider's token to besides its own
+    pub trusted_hosts: Option<Vec<String>>,
+}
+
+impl SecurityConfig {
+    fn merge(&mut self, other: SecurityConfig) {
+        if other.strict.is_some() {
+            self.strict = other.strict;
+        }
+        if other.trusted_hosts.is_some() {
+            self.trusted_hosts = other.trusted_hosts;
+        }
+    }
+
+    /// Names of the fields that are set
+    fn set_fields(&self) -> Vec<&'static str> {
+        let mut fields = Vec::new();
+        if self.strict.is_some() {
+            fields.push("strict");
+        }
+        if self.trusted_hosts.is_some() {
+            fields.push("trusted_hosts");
+        }
+        fields
+    }
+}
+
+/// A named profile (`[profiles.work]`) with its own defaults and providers
+#[derive(Debug, Clone, Default, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct ProfileConfig {
+    /// Keep the top-level providers, with their tokens and the `*_TOKEN`
+    /// environment variables, under this profile's own. Off by default, so a
+    /// profile only sees the providers it defines.
+    pub inherit_providers: Option<bool>,
+
+    #[serde(default)]
+    pub defaults: DefaultsConfig,
+
+    #[serde(default)]
+    pub providers: HashMap<String, ProviderEntry>,
+}
+
+impl ProfileConfig {
+    fn merge(&mut self, other: ProfileConfig) {
+        if other.inherit_providers.is_some() {
+            self.inherit_providers = other.inherit_providers;
+        }
+        self.defaults.merge(other.defaults);
+        merge_providers(&mut self.providers, other.providers);
     }
 }
 
 /// A named provider entry in the config
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Default, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
 pub struct ProviderEntry {
     /// Provider type (github, gitlab, bitbucket)
     /// Optional for entries named "github", "gitlab", or "bitbucket"
@@ -77,10 +387,117 @@ pub struct ProviderEntry {
     pub provider_type: Option<ProviderType>,
     pub token: Option<String>,
     pub url: Option<String>,
+
+    /// Default owner, group or workspace to scope searches to
+    pub owner: Option<String>,
+    /// Whether archived repositories are included (provider default if unset)
+    pub include_archived: Option<bool>,
+    /// Extra provider-native query appended to every search
+    /// (GitHub qualifiers, GitLab query parameters, Bitbucket BBQL)
+    pub extra_query: Option<String>,
+    /// Default result limit for this provider
+    pub limit: Option<usize>,
+    /// How much this provider's results count when ranking merged results (default 1.0)
+    pub weight: Option<f64>,
+    /// OAuth application (client) ID used by `auth login`
+    pub client_id: Option<String>,
+    /// OAuth client secret (Bitbucket OAuth consumers)
+    pub client_secret: Option<String>,
+    /// Authenticate as a GitHub App instead of with a static token
+    pub github_app: Option<GitHubAppConfig>,
+}
+
+impl ProviderEntry {
+    fn merge(&mut self, other: ProviderEntry) {
+        if other.provider_type.is_some() {
+            self.provider_type = other.provider_type;
+        }
+        if other.token.is_some() {
+            self.token = other.token;
+        }
+        if other.url.is_some() {
+            self.url = other.url;
+        }
+        if other.owner.is_some() {
+            self.owner = other.owner;
+        }
+        if other.include_archived.is_some() {
+            self.include_archived = other.include_archived;
+        }
+        if other.extra_query.is_some() {
+            self.extra_query = other.extra_query;
+        }
+        if other.limit.is_some() {
+            self.limit = other.limit;
+        }
+        if other.weight.is_some() {
+            self.weight = other.weight;
+        }
+        if other.client_id.is_some() {
+            self.client_id = other.client_id;
+        }
+        if other.client_secret.is_some() {
+            self.client_secret = other.client_secret;
+        }
+        if other.github_app.is_some() {
+            self.github_app = other.github_app;
+        }
+    }
+
+    /// Names of the fields that are set on this entry
+    fn set_fields(&self) -> Vec<&'static str> {
+        let mut fields = Vec::new();
+        if self.provider_type.is_some() {
+            fields.push("type");
+        }
+        if self.token.is_some() {
+            fields.push("token");
+        }
+        if self.url.is_some() {
+            fields.push("url");
+        }
+        if self.owner.is_some() {
+            fields.push("owner");
+        }
+        if self.include_archived.is_some() {
+            fields.push("include_archived");
+        }
+        if self.extra_query.is_some() {
+            fields.push("extra_query");
+        }
+        if self.limit.is_some() {
+            fields.push("limit");
+        }
+        if self.weight.is_some() {
+            fields.push("weight");
+        }
+        if self.client_id.is_some() {
+            fields.push("client_id");
+        }
+        if self.client_secret.is_some() {
+            fields.push("client_secret");
+        }
+        if self.github_app.is_some() {
+            fields.push("github_app");
+        }
+        fields
+    }
+}
+
+/// GitHub App credentials (`[providers.<name>.github_app]`)
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct GitHubAppConfig {
+    pub app_id: u64,
+    /// Path to the app's private key PEM
+    pub private_key_path: PathBuf,
+    /// Installation to use when the search has no owner to pick one by
+    pub installation_id: Option<u64>,
 }
 
 /// Legacy provider config (top-level [github], [gitlab], [bitbucket])
-#[derive(Debug, Clone, Default, Deserialize)]
+#[derive(Debug, Clone, Default, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
 pub struct LegacyProviderConfig {
     pub token: Option<String>,
     pub url: Option<String>,
@@ -93,32 +510,607 @@ pub struct ResolvedProvider {
     pub provider_type: ProviderType,
     pub token: Option<String>,
     pub url: String,
+    pub scope: ProviderScope,
+    pub oauth: OAuthClient,
+    /// Stored `auth login` credential backing `token`, if any
+    pub credential: Option<StoredCredential>,
+    pub github_app: Option<GitHubAppConfig>,
+}
+
+impl ResolvedProvider {
+    /// Secrets that must never appear in output for this provider
+    pub fn secrets(&self) -> Vec<&str> {
+        let mut secrets = Vec::new();
+        secrets.extend(self.token.as_deref());
+        secrets.extend(self.oauth.client_secret.as_deref());
+        if let Some(credential) = &self.credential {
+            secrets.push(credential.access_token.as_str());
+            secrets.extend(credential.refresh_token.as_deref());
+        }
+        secrets
+    }
+
+    /// Whether requests to this provider carry a token or app credentials
+    pub fn has_credentials(&self) -> bool {
+        self.token.is_some() || self.github_app.is_some()
+    }
+}
+
+/// OAuth application used by `auth login` and token refresh
+#[derive(Debug, Clone, Default)]
+pub struct OAuthClient {
+    pub client_id: Option<String>,
+    pub client_secret: Option<String>,
+}
+
+/// Per-provider search defaults, applied when the CLI does not override them
+#[derive(Debug, Clone, Default)]
+pub struct ProviderScope {
+    pub owner: Option<String>,
+    pub include_archived: Option<bool>,
+    pub extra_query: Option<String>,
+    pub limit: Option<usize>,
+    pub weight: Option<f64>,
+}
+
+/// Severity of a problem found by `Config::check`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+/// A single problem found while validating a provider
+#[derive(Debug, Clone)]
+pub struct CheckIssue {
+    pub severity: Severity,
+    pub message: String,
+}
+
+/// Validation result for one provider
+#[derive(Debug, Clone)]
+pub struct ProviderCheck {
+    pub name: String,
+    pub provider_type: Option<ProviderType>,
+    pub url: Option<String>,
+    pub issues: Vec<CheckIssue>,
+}
+
+impl ProviderCheck {
+    pub fn has_errors(&self) -> bool {
+        self.issues
+            .iter()
+            .any(|issue| issue.severity == Severity::Error)
+    }
 }
 
 impl Config {
-    pub fn load() -> Result<Self> {
-        let mut config = Self::load_from_file().unwrap_or_default();
+    /// JSON Schema for the config file, derived from the serde structs
+    pub fn json_schema() -> String {
+        let schema = schemars::schema_for!(Config);
+        serde_json::to_string_pretty(&schema).expect("schema serializes to JSON") + "\n"
+    }
+
+    /// Load and merge the system, user, project-local and explicit config files,
+    /// then apply environment overrides
+    pub fn load(options: &LoadOptions) -> Result<Self> {
+        let mut config = Self::load_layers(&Self::layer_paths(options)?)?;
+        if let Some(profile) = &options.profile {
+            config.apply_profile(profile)?;
+        }
         config.apply_env_overrides();
-        config.migrate_legacy_providers();
+        config.check_permissions()?;
+        config.check_untrusted_urls()?;
+        config.apply_cached_types(&DetectionCache::load());
+        Ok(config)
+    }
+
+    /// Fill in types detected on an earlier run
+    fn apply_cached_types(&mut self, cache: &DetectionCache) {
+        let names = self.provider_names();
+        for (name, url, _) in self.undetected_providers(&names) {
+            if let Some(detected) = cache.get(&url) {
+                self.set_detected_type(&name, detected);
+            }
+        }
+    }
+
+    /// Providers among `names` with a URL but no type, as (name, url, token)
+    pub fn undetected_providers(&self, names: &[String]) -> Vec<(String, String, Option<String>)> {
+        names
+            .iter()
+            .filter(|name| ProviderType::from_name(name).is_none())
+            .filter_map(|name| {
+                let entry = self.providers.get(name)?;
+                if entry.provider_type.is_some() {
+                    return None;
+                }
+                Some((name.clone(), entry.url.clone()?, entry.token.clone()))
+            })
+            .collect()
+    }
+
+    /// Record a probed type, and the normalised URL, for provider `name`
+    pub fn set_detected_type(&mut self, name: &str, detected: &Detected) {
+        let Some(entry) = self.providers.get_mut(name) else {
+            return;
+        };
+        entry.provider_type = Some(detected.provider_type);
+        self.sources
+            .insert(format!("providers.{}.type", name), ValueSource::Detected);
+        if entry.url.as_deref() != Some(detected.url.as_str()) {
+            entry.url = Some(detected.url.clone());
+            self.sources
+                .insert(format!("providers.{}.url", name), ValueSource::Detected);
+        }
+    }
+
+    /// Warn about (or, with `security.strict`, refuse) readable files with tokens
+    fn check_permissions(&self) -> Result<(), ConfigError> {
+        for (path, mode) in &self.insecure_files {
+            let error = ConfigError::InsecurePermissions {
+                path: path.clone(),
+                mode: *mode,
+            };
+            if self.security.strict == Some(true) {
+                return Err(error);
+            }
+            eprintln!("Warning: {}", error);
+        }
+        Ok(())
+    }
+
+    /// Refuse provider URLs from project-local or included files that would
+    /// receive a token configured somewhere else
+    fn check_untrusted_urls(&self) -> Result<(), ConfigError> {
+        for (key, path) in &self.untrusted_urls {
+            let Some(name) = key.strip_prefix("providers.") else {
+                continue;
+            };
+            let has_token = self
+                .providers
+                .get(name)
+                .is_some_and(|entry| entry.token.is_some());
+            if has_token || self.stored_credential(name).is_some() {
+                return Err(ConfigError::UntrustedUrl {
+                    path: path.clone(),
+                    name: name.to_string(),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Overlay the named profile's defaults on the base config and switch to
+    /// its providers, keeping the base providers only if it inherits them
+    fn apply_profile(&mut self, name: &str) -> Result<(), ConfigError> {
+        let Some(profile) = self.profiles.get(name).cloned() else {
+            let suggestion = self
+                .profiles
+                .keys()
+                .map(|candidate| (levenshtein(name, candidate), candidate))
+                .filter(|(distance, _)| *distance <= 2)
+                .min_by_key(|(distance, _)| *distance)
+                .map(|(_, candidate)| candidate.clone());
+            return Err(ConfigError::UnknownProfile {
+                name: name.to_string(),
+                suggestion,
+            });
+        };
+
+        let prefix = format!("profiles.{}.", name);
+        if profile.inherit_providers != Some(true) {
+            self.providers.clear();
+            self.untrusted_urls
+                .retain(|key, _| !key.starts_with("providers."));
+            self.sources.retain(|key, _| !key.starts_with("providers."));
+        }
+        for (provider, entry) in &profile.providers {
+            if entry.url.is_some() {
+                self.untrusted_urls
+                    .remove(&format!("providers.{}", provider));
+            }
+        }
+        let profile_urls: Vec<(String, PathBuf)> = self
+            .untrusted_urls
+            .iter()
+            .filter_map(|(key, path)| {
+                key.strip_prefix(&prefix)
+                    .map(|key| (key.to_string(), path.clone()))
+            })
+            .collect();
+        self.untrusted_urls.extend(profile_urls);
+
+        self.defaults.merge(profile.defaults);
+        merge_providers(&mut self.providers, profile.providers);
+
+        let profile_sources: Vec<(String, ValueSource)> = self
+            .sources
+            .iter()
+            .filter_map(|(key, source)| {
+                key.strip_prefix(&prefix)
+                    .map(|key| (key.to_string(), source.clone()))
+            })
+            .collect();
+        self.sources.extend(profile_sources);
+
+        self.active_profile = Some(name.to_string());
+        Ok(())
+    }
+
+    /// Name of the profile applied while loading, if any
+    pub fn active_profile(&self) -> Option<&str> {
+        self.active_profile.as_deref()
+    }
+
+    /// Whether the top-level providers and environment tokens apply: always
+    /// without a profile, and under one that sets `inherit_providers`
+    fn inherits_providers(&self) -> bool {
+        self.active_profile.as_ref().is_none_or(|name| {
+            self.profiles
+                .get(name)
+                .is_some_and(|profile| profile.inherit_providers == Some(true))
+        })
+    }
+
+    /// Config files to merge, lowest precedence first
+    fn layer_paths(options: &LoadOptions) -> Result<Vec<PathBuf>> {
+        let mut paths = Vec::new();
+
+        if let Some(system) = SYSTEM_CONFIG_PATH.map(PathBuf::from) {
+            if system.exists() {
+                paths.push(system);
+            }
+        }
+
+        let user = Self::config_path()?;
+        if user.exists() {
+            paths.push(user);
+        }
+
+        if let Some(project) = Self::find_project_config() {
+            if !paths.contains(&project) {
+                paths.push(project);
+            }
+        }
+
+        if let Some(explicit) = &options.config_file {
+            if !explicit.exists() {
+                return Err(ConfigError::NotFound {
+                    path: explicit.clone(),
+                }
+                .into());
+            }
+            paths.push(explicit.clone());
+        }
+
+        Ok(paths)
+    }
+
+    /// Walk up from the current directory looking for a project-local config
+    fn find_project_config() -> Option<PathBuf> {
+        let cwd = env::current_dir().ok()?;
+        cwd.ancestors()
+            .map(|dir| dir.join(PROJECT_CONFIG_FILE))
+            .find(|path| path.is_file())
+    }
+
+    fn load_layers(paths: &[PathBuf]) -> Result<Self> {
+        let mut config = Self::default();
+        for path in paths {
+            // A project-local file comes with whatever repository was cloned
+            let trusted = path.file_name() != Some(PROJECT_CONFIG_FILE.as_ref());
+            config.merge(Self::load_file(path, trusted, &mut Vec::new())?);
+        }
         Ok(config)
     }
 
-    fn load_from_file() -> Result<Self> {
-        let config_path = Self::config_path()?;
-        if !config_path.exists() {
-            return Ok(Self::default());
+    /// Load one file, merging its includes underneath it. Untrusted files
+    /// (project-local ones and anything included) have their tokenless
+    /// provider URLs recorded for `check_untrusted_urls`.
+    fn load_file(path: &Path, trusted: bool, chain: &mut Vec<PathBuf>) -> Result<Self> {
+        let canonical = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+        if chain.contains(&canonical) {
+            return Err(ConfigError::IncludeCycle {
+                path: path.to_path_buf(),
+            }
+            .into());
+        }
+
+        let content = fs::read_to_string(path).map_err(|source| ConfigError::Read {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        let mut layer = Self::parse(path, &content)?;
+        if !trusted {
+            layer.restrict_security(path);
+        }
+        if let Some(identity) = &layer.credentials.identity {
+            let identity = resolve_include(path, &identity.to_string_lossy());
+            layer.credentials.identity = Some(identity);
+        }
+        layer.record_file_sources(path);
+        if layer.has_secrets() {
+            if let Some(mode) = security::insecure_mode(path) {
+                layer.insecure_files.push((path.to_path_buf(), mode));
+            }
+        }
+        layer.warn_legacy_sections(path);
+        layer.migrate_legacy_providers();
+        if !trusted {
+            layer.record_untrusted_urls(path);
+        }
+        layer.files.push(path.to_path_buf());
+
+        let mut config = Self::default();
+        chain.push(canonical);
+        for include in std::mem::take(&mut layer.include) {
+            let include_path = resolve_include(path, &include);
+            if !include_path.exists() {
+                return Err(ConfigError::NotFound { path: include_path }.into());
+            }
+            config.merge(Self::load_file(&include_path, false, chain)?);
         }
-        let content = fs::read_to_string(&config_path)?;
-        let config: Config = toml::from_str(&content)?;
+        chain.pop();
+
+        config.merge(layer);
         Ok(config)
     }
 
+    /// Overlay `other` on top of `self`, field by field
+    fn merge(&mut self, other: Config) {
+        // A later URL replaces an untrusted one; untrusted ones are re-added below
+        for (key, entry) in other.provider_sections() {
+            if entry.url.is_some() {
+                self.untrusted_urls.remove(&key);
+            }
+        }
+        self.untrusted_urls.extend(other.untrusted_urls);
+
+        self.defaults.merge(other.defaults);
+        merge_providers(&mut self.providers, other.providers);
+        self.groups.extend(other.groups);
+        self.credentials.merge(other.credentials);
+        self.security.merge(other.security);
+
+        for (name, profile) in other.profiles {
+            self.profiles.entry(name).or_default().merge(profile);
+        }
+
+        self.sources.extend(other.sources);
+        self.files.extend(other.files);
+        self.insecure_files.extend(other.insecure_files);
+    }
+
+    /// Every provider entry, base and per-profile, keyed like `sources`
+    fn provider_sections(&self) -> Vec<(String, &ProviderEntry)> {
+        let base = self
+            .providers
+            .iter()
+            .map(|(name, entry)| (format!("providers.{}", name), entry));
+        let profiles = self.profiles.iter().flat_map(|(profile, section)| {
+            section.providers.iter().map(move |(name, entry)| {
+                (format!("profiles.{}.providers.{}", profile, name), entry)
+            })
+        });
+        base.chain(profiles).collect()
+    }
+
+    /// Drop `[security]` settings in a freshly parsed untrusted file that
+    /// would loosen the safeguards; turning on `strict` is still allowed
+    fn restrict_security(&mut self, path: &Path) {
+        let mut ignored = Vec::new();
+        if self.security.strict == Some(false) {
+            self.security.strict = None;
+            ignored.push("strict");
+        }
+        if self.security.trusted_hosts.take().is_some() {
+            ignored.push("trusted_hosts");
+        }
+        if !ignored.is_empty() {
+            eprintln!(
+                "Warning: Ignoring security.{} in {}; set it in the user or system config",
+                ignored.join(" and security."),
+                path.display()
+            );
+        }
+    }
+
+    /// Note provider URLs in a freshly parsed untrusted file that come
+    /// without a token of their own
+    fn record_untrusted_urls(&mut self, path: &Path) {
+        let keys: Vec<String> = self
+            .provider_sections()
+            .into_iter()
+            .filter(|(_, entry)| entry.url.is_some() && entry.token.is_none())
+            .map(|(key, _)| key)
+            .collect();
+        for key in keys {
+            self.untrusted_urls.insert(key, path.to_path_buf());
+        }
+    }
+
+    /// Whether a freshly parsed file holds tokens or client secrets
+    fn has_secrets(&self) -> bool {
+        let entries = self
+            .providers
+            .values()
+            .chain(self.profiles.values().flat_map(|p| p.providers.values()));
+        let legacy = [&self.github, &self.gitlab, &self.bitbucket];
+        entries
+            .into_iter()
+            .any(|entry| entry.token.is_some() || entry.client_secret.is_some())
+            || legacy
+                .iter()
+                .any(|legacy| legacy.as_ref().is_some_and(|l| l.token.is_some()))
+    }
+
+    /// Config files that contributed to this config, in load order
+    pub fn files(&self) -> &[PathBuf] {
+        &self.files
+    }
+
+    fn parse(path: &Path, content: &str) -> Result<Self, ConfigError> {
+        toml::from_str(content).map_err(|err| ConfigError::from_toml(path, content, err))
+    }
+
     pub fn config_path() -> Result<PathBuf> {
         let config_dir = dirs::config_dir()
             .ok_or_else(|| anyhow::anyhow!("Could not determine config directory"))?;
         Ok(config_dir.join("repo_search").join("config.toml"))
     }
 
+    /// Validate `content` as a config file and write it to `path`
+    pub fn write_file(path: &Path, content: &str) -> Result<()> {
+        Self::parse(path, content)?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        // The file usually holds tokens, so keep it private to the user
+        crate::security::write_private(path, content)?;
+        Ok(())
+    }
+
+    /// Record every value present in a freshly parsed file as coming from `path`
+    fn record_file_sources(&mut self, path: &Path) {
+        let mut keys = Vec::new();
+        let sections = std::iter::once((String::new(), &self.defaults, &self.providers)).chain(
+            self.profiles.iter().map(|(name, profile)| {
+                (
+                    format!("profiles.{}.", name),
+                    &profile.defaults,
+                    &profile.providers,
+                )
+            }),
+        );
+        for (prefix, defaults, providers) in sections {
+            for field in defaults.set_fields() {
+                keys.push(format!("{}defaults.{}", prefix, field));
+            }
+            for (name, entry) in providers {
+                for field in entry.set_fields() {
+                    keys.push(format!("{}providers.{}.{}", prefix, name, field));
+                }
+            }
+        }
+
+        for field in self.credentials.set_fields() {
+            keys.push(format!("credentials.{}", field));
+        }
+        for field in self.security.set_fields() {
+            keys.push(format!("security.{}", field));
+        }
+
+        // Legacy sections end up under their providers.* equivalents
+        for (name, legacy) in [
+            ("github", &self.github),
+            ("gitlab", &self.gitlab),
+            ("bitbucket", &self.bitbucket),
+        ] {
+            let Some(legacy) = legacy else { continue };
+            if legacy.token.is_some() {
+                keys.push(format!("providers.{}.token", name));
+            }
+            if legacy.url.is_some() {
+                keys.push(format!("providers.{}.url", name));
+            }
+        }
+
+        for key in keys {
+            self.sources
+                .entry(key)
+                .or_insert_with(|| ValueSource::File(path.to_path_buf()));
+        }
+    }
+
+    /// Where the effective value at `key` (e.g. `providers.github.token`) came from
+    pub fn source_of(&self, key: &str) -> ValueSource {
+        if let Some(source) = self.sources.get(key) {
+            return source.clone();
+        }
+
+        let stored_token = key
+            .strip_prefix("providers.")
+            .and_then(|rest| rest.strip_suffix(".token"))
+            .filter(|name| self.stored_credential(name).is_some());
+        match (
+            stored_token,
+            self.stored_credentials().and_then(|s| s.path()),
+        ) {
+            (Some(_), Some(path)) => ValueSource::Credentials(path.to_path_buf()),
+            _ => ValueSource::Default,
+        }
+    }
+
+    /// The credential store, loading (and decrypting) it on first use
+    fn load_store(&self) -> &Result<CredentialStore, String> {
+        self.store.get_or_init(|| {
+            CredentialStore::load(&self.credentials).map_err(|e| format!("{:#}", e))
+        })
+    }
+
+    /// A copy of the credential store for updating and saving
+    pub fn credential_store(&self) -> Result<CredentialStore> {
+        self.load_store().clone().map_err(anyhow::Error::msg)
+    }
+
+    /// Stored credentials, or `None` (with a warning) if they can't be read
+    fn stored_credentials(&self) -> Option<&CredentialStore> {
+        let first_use = self.store.get().is_none();
+        match self.load_store() {
+            Ok(store) => Some(store),
+            Err(e) => {
+                if first_use {
+                    eprintln!("Warning: Ignoring stored credentials: {}", e);
+                }
+                None
+            }
+        }
+    }
+
+    fn stored_credential(&self, name: &str) -> Option<&StoredCredential> {
+        self.stored_credentials()?.get(&self.credential_key(name))
+    }
+
+    /// Key for provider `name` in the credential store. Tokens stored while a
+    /// profile is active belong to that profile alone, like profile tokens in
+    /// the config file.
+    pub fn credential_key(&self, name: &str) -> String {
+        match &self.active_profile {
+            Some(profile) => format!("{}/{}", profile, name),
+            None => name.to_string(),
+        }
+    }
+
+    /// Deprecation notice for top-level `[github]`-style sections in `path`
+    fn warn_legacy_sections(&self, path: &Path) {
+        let legacy: Vec<String> = [
+            ("github", &self.github),
+            ("gitlab", &self.gitlab),
+            ("bitbucket", &self.bitbucket),
+        ]
+        .into_iter()
+        .filter(|(_, section)| section.is_some())
+        .map(|(name, _)| format!("[{}]", name))
+        .collect();
+        if legacy.is_empty() {
+            return;
+        }
+
+        let is_user_file = Self::config_path().is_ok_and(|user| user == path);
+        let command = if is_user_file {
+            "repo_search config migrate".to_string()
+        } else {
+            format!("repo_search --config {} config migrate", path.display())
+        };
+        eprintln!(
+            "Warning: {} uses deprecated {} section(s); run `{}` to move them under [providers]",
+            path.display(),
+            legacy.join(", "),
+            command
+        );
+    }
+
     /// Migrate legacy top-level provider configs to the providers map
     fn migrate_legacy_providers(&mut self) {
         if let Some(legacy) = self.github.take() {
@@ -128,6 +1120,7 @@ impl Config {
                     provider_type: Some(ProviderType::Github),
                     token: legacy.token,
                     url: legacy.url,
+                    ..Default::default()
                 });
         }
         if let Some(legacy) = self.gitlab.take() {
@@ -137,6 +1130,7 @@ impl Config {
                     provider_type: Some(ProviderType::Gitlab),
                     token: legacy.token,
                     url: legacy.url,
+                    ..Default::default()
                 });
         }
         if let Some(legacy) = self.bitbucket.take() {
@@ -146,31 +1140,43 @@ impl Config {
                     provider_type: Some(ProviderType::Bitbucket),
                     token: legacy.token,
                     url: legacy.url,
+                    ..Default::default()
                 });
         }
     }
 
     fn apply_env_overrides(&mut self) {
+        // A profile with its own providers doesn't pick up tokens from the environment
+        if !self.inherits_providers() {
+            return;
+        }
         // Apply env overrides to named providers or create them
         if let Ok(token) = env::var("GITHUB_TOKEN") {
             self.providers
                 .entry("github".to_string())
                 .or_insert(ProviderEntry {
                     provider_type: Some(ProviderType::Github),
-                    token: None,
-                    url: None,
+                    ..Default::default()
                 })
                 .token = Some(token);
+            self.sources.insert(
+                "providers.github.token".to_string(),
+                ValueSource::Env("GITHUB_TOKEN"),
+            );
         }
         if let Ok(url) = env::var("GITHUB_URL") {
             self.providers
                 .entry("github".to_string())
                 .or_insert(ProviderEntry {
                     provider_type: Some(ProviderType::Github),
-                    token: None,
-                    url: None,
+                    ..Default::default()
                 })
                 .url = Some(url);
+            self.sources.insert(
+                "providers.github.url".to_string(),
+                ValueSource::Env("GITHUB_URL"),
+            );
+            self.untrusted_urls.remove("providers.github");
         }
 
         if let Ok(token) = env::var("GITLAB_TOKEN") {
@@ -178,20 +1184,27 @@ impl Config {
                 .entry("gitlab".to_string())
                 .or_insert(ProviderEntry {
                     provider_type: Some(ProviderType::Gitlab),
-                    token: None,
-                    url: None,
+                    ..Default::default()
                 })
                 .token = Some(token);
+            self.sources.insert(
+                "providers.gitlab.token".to_string(),
+                ValueSource::Env("GITLAB_TOKEN"),
+            );
         }
         if let Ok(url) = env::var("GITLAB_URL") {
             self.providers
                 .entry("gitlab".to_string())
                 .or_insert(ProviderEntry {
                     provider_type: Some(ProviderType::Gitlab),
-                    token: None,
-                    url: None,
+                    ..Default::default()
                 })
                 .url = Some(url);
+            self.sources.insert(
+                "providers.gitlab.url".to_string(),
+                ValueSource::Env("GITLAB_URL"),
+            );
+            self.untrusted_urls.remove("providers.gitlab");
         }
 
         if let Ok(token) = env::var("BITBUCKET_TOKEN") {
@@ -199,53 +1212,77 @@ impl Config {
                 .entry("bitbucket".to_string())
                 .or_insert(ProviderEntry {
                     provider_type: Some(ProviderType::Bitbucket),
-                    token: None,
-                    url: None,
+                    ..Default::default()
                 })
                 .token = Some(token);
+            self.sources.insert(
+                "providers.bitbucket.token".to_string(),
+                ValueSource::Env("BITBUCKET_TOKEN"),
+            );
         }
         if let Ok(url) = env::var("BITBUCKET_URL") {
             self.providers
                 .entry("bitbucket".to_string())
                 .or_insert(ProviderEntry {
                     provider_type: Some(ProviderType::Bitbucket),
-                    token: None,
-                    url: None,
+                    ..Default::default()
                 })
                 .url = Some(url);
+            self.sources.insert(
+                "providers.bitbucket.url".to_string(),
+                ValueSource::Env("BITBUCKET_URL"),
+            );
+            self.untrusted_urls.remove("providers.bitbucket");
         }
     }
 
     /// Resolve a provider by name, returning its full configuration
     pub fn resolve_provider(&self, name: &str) -> Option<ResolvedProvider> {
-        // Check if it's a configured provider
-        if let Some(entry) = self.providers.get(name) {
-            let provider_type = entry
-                .provider_type
-                .or_else(|| ProviderType::from_name(name))?;
-
-            return Some(ResolvedProvider {
-                name: name.to_string(),
-                provider_type,
-                token: entry.token.clone(),
-                url: entry
-                    .url
-                    .clone()
-                    .unwrap_or_else(|| provider_type.default_url().to_string()),
-            });
-        }
+        // Configured providers may omit the type if the name is a built-in one;
+        // unconfigured built-in names resolve with their defaults
+        let default_entry = ProviderEntry::default();
+        let (entry, provider_type) = match self.providers.get(name) {
+            Some(entry) => (
+                entry,
+                entry
+                    .provider_type
+                    .or_else(|| ProviderType::from_name(name))?,
+            ),
+            None => (&default_entry, ProviderType::from_name(name)?),
+        };
 
-        // Check if it's a built-in provider name without config
-        if let Some(provider_type) = ProviderType::from_name(name) {
-            return Some(ResolvedProvider {
-                name: name.to_string(),
-                provider_type,
-                token: None,
-                url: provider_type.default_url().to_string(),
-            });
-        }
+        // Tokens from `auth login` are used only when no token is configured
+        let credential = match entry.token {
+            Some(_) => None,
+            None => self.stored_credential(name).cloned(),
+        };
+        let token = entry
+            .token
+            .clone()
+            .or_else(|| credential.as_ref().map(|c| c.access_token.clone()));
 
-        None
+        Some(ResolvedProvider {
+            name: name.to_string(),
+            provider_type,
+            token,
+            url: entry
+                .url
+                .clone()
+                .unwrap_or_else(|| provider_type.default_url().to_string()),
+            scope: ProviderScope {
+                owner: entry.owner.clone(),
+                include_archived: entry.include_archived,
+                extra_query: entry.extra_query.clone(),
+                limit: entry.limit,
+                weight: entry.weight,
+            },
+            oauth: OAuthClient {
+                client_id: entry.client_id.clone(),
+                client_secret: entry.client_secret.clone(),
+            },
+            credential,
+            github_app: entry.github_app.clone(),
+        })
     }
 
     /// Get all configured provider names
@@ -272,15 +1309,261 @@ impl Config {
         })
     }
 
+    /// Get all group names, sorted
+    pub fn group_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.groups.keys().cloned().collect();
+        names.sort();
+        names
+    }
+
+    /// Expand `all` and group names into provider names, keeping the first
+    /// occurrence of each in order
+    pub fn expand_provider_names(&self, requested: &[String]) -> Vec<String> {
+        let mut names = Vec::new();
+        for name in requested {
+            self.expand_name(name, &mut Vec::new(), &mut names);
+        }
+        names
+    }
+
+    fn expand_name(&self, name: &str, visiting: &mut Vec<String>, names: &mut Vec<String>) {
+        let expanded = if name.eq_ignore_ascii_case("all") {
+            self.provider_names()
+        } else if let Some(members) = self.groups.get(name) {
+            // A group that (indirectly) contains itself is expanded only once
+            if visiting.iter().any(|group| group == name) {
+                return;
+            }
+            visiting.push(name.to_string());
+            for member in members {
+                self.expand_name(member, visiting, names);
+            }
+            visiting.pop();
+            return;
+        } else {
+            vec![name.to_string()]
+        };
+
+        for name in expanded {
+            if !names.contains(&name) {
+                names.push(name);
+            }
+        }
+    }
+
+    /// Validate every configured and default provider without searching
+    pub fn check(&self) -> Vec<ProviderCheck> {
+        let mut names = self.provider_names();
+        let mut referenced = self.default_providers();
+        referenced.extend(self.group_names());
+        for name in self.expand_provider_names(&referenced) {
+            if !names.contains(&name) {
+                names.push(name);
+            }
+        }
+
+        names.iter().map(|name| self.check_provider(name)).collect()
+    }
+
+    fn check_provider(&self, name: &str) -> ProviderCheck {
+        let mut issues = Vec::new();
+
+        let resolved = self.resolve_provider(name);
+        if resolved.is_none() {
+            let message = if self.providers.get(name).is_some_and(|e| e.url.is_some()) {
+                "type could not be detected from the URL; set `type` to github, gitlab or bitbucket"
+                    .to_string()
+            } else if self.providers.contains_key(name) {
+                "type cannot be inferred from the name; set `type` to github, gitlab or bitbucket"
+                    .to_string()
+            } else {
+                "referenced in defaults.providers or a group but not configured".to_string()
+            };
+            issues.push(CheckIssue {
+                severity: Severity::Error,
+                message,
+            });
+        }
+
+        if let Some(resolved) = &resolved {
+            if let Err(message) = validate_url(&resolved.url) {
+                issues.push(CheckIssue {
+                    severity: Severity::Error,
+                    message,
+                });
+            }
+
+            if let Some(weight) = resolved.scope.weight {
+                if !weight.is_finite() || weight <= 0.0 {
+                    issues.push(CheckIssue {
+                        severity: Severity::Error,
+                        message: format!("weight must be a positive number, not {}", weight),
+                    });
+                }
+            }
+
+            if let Some(app) = &resolved.github_app {
+                if resolved.provider_type != ProviderType::Github {
+                    issues.push(CheckIssue {
+                        severity: Severity::Error,
+                        message: "github_app is only supported for GitHub providers".to_string(),
+                    });
+                }
+                if !app.private_key_path.is_file() {
+                    issues.push(CheckIssue {
+                        severity: Severity::Error,
+                        message: format!(
+                            "GitHub App private key not found: {}",
+                            app.private_key_path.display()
+                        ),
+                    });
+                }
+            } else if resolved.token.is_none() {
+                let message = match resolved.provider_type {
+                    ProviderType::Bitbucket => {
+                        "no token configured; Bitbucket searches will fail without --mine or --owner"
+                    }
+                    _ => "no token configured; only public repositories will be found",
+                };
+                issues.push(CheckIssue {
+                    severity: Severity::Warning,
+                    message: message.to_string(),
+                });
+            }
+        }
+
+        ProviderCheck {
+            name: name.to_string(),
+            provider_type: resolved.as_ref().map(|r| r.provider_type),
+            url: resolved.map(|r| r.url),
+            issues,
+        }
+    }
+
     /// Parse config from a TOML string (for testing)
     #[cfg(test)]
     pub fn from_toml(content: &str) -> Result<Self> {
-        let mut config: Config = toml::from_str(content)?;
+        let path = Path::new("config.toml");
+        let mut config = Self::parse(path, content)?;
+        config.record_file_sources(path);
         config.migrate_legacy_providers();
+        // Keep tests away from the real credentials file
+        config.store = OnceLock::from(Ok(CredentialStore::default()));
         Ok(config)
     }
 }
 
+/// Overlay `other` provider entries on `target`, field by field
+fn merge_providers(
+    target: &mut HashMap<String, ProviderEntry>,
+    other: HashMap<String, ProviderEntry>,
+) {
+    for (name, entry) in other {
+        match target.get_mut(&name) {
+            Some(existing) => existing.merge(entry),
+            None => {
+                target.insert(name, entry);
+            }
+        }
+    }
+}
+
+/// Resolve an `include` entry relative to the file that names it
+fn resolve_include(including_file: &Path, include: &str) -> PathBuf {
+    let path = match include.strip_prefix("~/") {
+        Some(rest) => dirs::home_dir()
+            .map(|home| home.join(rest))
+            .unwrap_or_else(|| PathBuf::from(include)),
+        None => PathBuf::from(include),
+    };
+    if path.is_absolute() {
+        return path;
+    }
+    including_file
+        .parent()
+        .map(|dir| dir.join(&path))
+        .unwrap_or(path)
+}
+
+/// Check that a provider URL is an absolute http(s) URL with a host
+fn validate_url(url: &str) -> Result<(), String> {
+    let parsed = Url::parse(url).map_err(|e| format!("invalid URL '{}': {}", url, e))?;
+    if !matches!(parsed.scheme(), "http" | "https") {
+        return Err(format!(
+            "invalid URL '{}': scheme must be http or https",
+            url
+        ));
+    }
+    if parsed.host_str().is_none() {
+        return Err(format!("invalid URL '{}': missing host", url));
+    }
+    Ok(())
+}
+
+/// Convert a byte offset into a 1-based line and column
+fn line_column(content: &str, offset: usize) -> (usize, usize) {
+    let before = &content[..offset.min(content.len())];
+    let line = before.matches('\n').count() + 1;
+    let column = before
+        .rfind('\n')
+        .map_or(before.len(), |newline| before.len() - newline - 1)
+        + 1;
+    (line, column)
+}
+
+/// Best-effort dotted key for the assignment on `line`, qualified by the
+/// nearest table header above it
+fn key_at_line(content: &str, line: usize) -> Option<String> {
+    let lines: Vec<&str> = content.lines().collect();
+    let text = lines.get(line.checked_sub(1)?)?.trim();
+    let (key, _) = text.split_once('=')?;
+    let key = key.trim();
+    if key.is_empty() {
+        return None;
+    }
+
+    let table = lines[..line - 1].iter().rev().find_map(|l| {
+        let l = l.trim();
+        l.strip_prefix('[')
+            .and_then(|rest| rest.strip_suffix(']'))
+            .map(|header| header.trim_matches(|c| c == '[' || c == ']').trim())
+    });
+
+    Some(match table {
+        Some(table) => format!("{}.{}", table, key),
+        None => key.to_string(),
+    })
+}
+
+/// Suggest the closest known field for serde's "unknown field" errors
+fn suggest_field(message: &str) -> Option<String> {
+    let rest = message.strip_prefix("unknown field `")?;
+    let (unknown, expected) = rest.split_once('`')?;
+    expected
+        .split('`')
+        .skip(1)
+        .step_by(2)
+        .map(|candidate| (levenshtein(unknown, candidate), candidate))
+        .filter(|(distance, candidate)| *distance <= 2.max(candidate.len() / 3))
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, candidate)| candidate.to_string())
+}
+
+pub(crate) fn levenshtein(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut previous = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous + usize::from(ca != *cb);
+            previous = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(previous + 1);
+        }
+    }
+    row[b.len()]
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -519,4 +1802,559 @@ mod tests {
         // Should use GitHub's default URL since none was specified
         assert_eq!(provider.url, "https://api.github.com");
     }
+
+    #[test]
+    fn test_unknown_provider_key_is_rejected_with_suggestion() {
+        let toml = r#"
+            [providers.work-gitlab]
+            type = "gitlab"
+            tokn = "work-token"
+        "#;
+        let err = Config::parse(Path::new("config.toml"), toml).unwrap_err();
+
+        match err {
+            ConfigError::Parse {
+                line,
+                key,
+                suggestion,
+                ..
+            } => {
+                assert_eq!(line, 4);
+                assert_eq!(key.as_deref(), Some("providers.work-gitlab.tokn"));
+                assert_eq!(suggestion.as_deref(), Some("token"));
+            }
+            other => panic!("unexpected error: {}", other),
+        }
+    }
+
+    #[test]
+    fn test_unknown_defaults_key_is_rejected() {
+        let toml = r#"
+            [defaults]
+            limt = 5
+        "#;
+        let err = Config::from_toml(toml).unwrap_err().to_string();
+        assert!(err.contains("config.toml:3:13"), "{}", err);
+        assert!(err.contains("`defaults.limt`"), "{}", err);
+        assert!(err.contains("did you mean `limit`?"), "{}", err);
+    }
+
+    #[test]
+    fn test_parse_error_reports_value_location() {
+        let toml = "[defaults]\nlimit = \"ten\"\n";
+        let err = Config::from_toml(toml).unwrap_err().to_string();
+        assert!(err.contains("config.toml:2:9"), "{}", err);
+        assert!(err.contains("`defaults.limit`"), "{}", err);
+    }
+
+    #[test]
+    fn test_check_reports_unresolvable_and_invalid_providers() {
+        let toml = r#"
+            [defaults]
+            providers = ["github", "missing"]
+
+            [providers.github]
+            token = "ghp_test"
+
+            [providers.my-custom]
+            url = "https://custom.com"
+
+            [providers.bad-url]
+            type = "gitlab"
+            url = "gitlab.work.com"
+            token = "token"
+        "#;
+        let config = Config::from_toml(toml).unwrap();
+        let checks = config.check();
+        let find = |name: &str| checks.iter().find(|c| c.name == name).unwrap();
+
+        assert!(!find("github").has_errors());
+        assert!(find("github").issues.is_empty());
+        assert!(find("my-custom").has_errors());
+        assert!(find("missing").has_errors());
+        assert!(find("bad-url").has_errors());
+        assert!(!find("gitlab").has_errors());
+        assert_eq!(find("gitlab").issues[0].severity, Severity::Warning);
+    }
+
+    #[test]
+    fn test_sources_track_file_and_defaults() {
+        let toml = r#"
+            [defaults]
+            limit = 5
+
+            [providers.work-gitlab]
+            type = "gitlab"
+            url = "https://gitlab.work.com"
+
+            [github]
+            token = "legacy-token"
+        "#;
+        let config = Config::from_toml(toml).unwrap();
+        let file = ValueSource::File(PathBuf::from("config.toml"));
+
+        assert_eq!(config.source_of("defaults.limit"), file);
+        assert_eq!(config.source_of("defaults.providers"), ValueSource::Default);
+        assert_eq!(config.source_of("providers.work-gitlab.url"), file);
+        assert_eq!(
+            config.source_of("providers.work-gitlab.token"),
+            ValueSource::Default
+        );
+        assert_eq!(config.source_of("providers.github.token"), file);
+    }
+
+    #[test]
+    fn test_shipped_schema_is_current() {
+        // Regenerate with `repo_search config schema > config.schema.json`
+        assert_eq!(
+            Config::json_schema(),
+            include_str!("../config.schema.json"),
+            "config.schema.json is out of date"
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_readable_file_with_tokens() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+        let secret = dir.join("config.toml");
+        let public = dir.join(PROJECT_CONFIG_FILE);
+        fs::write(&secret, "[providers.github]\ntoken = \"ghp_x\"\n").unwrap();
+        fs::write(&public, "[security]\nstrict = true\n").unwrap();
+        for path in [&secret, &public] {
+            fs::set_permissions(path, fs::Permissions::from_mode(0o644)).unwrap();
+        }
+
+        // Only the file that holds a token is flagged
+        let config = Config::load_layers(&[secret.clone(), public]).unwrap();
+        assert_eq!(config.insecure_files, vec![(secret.clone(), 0o644)]);
+        assert!(matches!(
+            config.check_permissions(),
+            Err(ConfigError::InsecurePermissions { mode: 0o644, .. })
+        ));
+
+        fs::set_permissions(&secret, fs::Permissions::from_mode(0o600)).unwrap();
+        let config = Config::load_layers(&[secret]).unwrap();
+        assert!(config.insecure_files.is_empty());
+    }
+
+    #[test]
+    fn test_layers_merge_in_order() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+        let user = dir.join("config.toml");
+        let project = dir.join(PROJECT_CONFIG_FILE);
+        fs::write(
+            &user,
+            r#"
+            [defaults]
+            providers = ["github"]
+            limit = 5
+
+            [providers.work-gitlab]
+            type = "gitlab"
+            url = "https://gitlab.work.com"
+            token = "user-token"
+            "#,
+        )
+        .unwrap();
+        fs::write(
+            &project,
+            r#"
+            [defaults]
+            providers = ["work-gitlab"]
+
+            [providers.work-gitlab]
+            owner = "platform"
+            "#,
+        )
+        .unwrap();
+
+        let config = Config::load_layers(&[user.clone(), project.clone()]).unwrap();
+
+        assert_eq!(config.default_providers(), vec!["work-gitlab".to_string()]);
+        assert_eq!(config.defaults.limit, Some(5));
+        let provider = config.resolve_provider("work-gitlab").unwrap();
+        assert_eq!(provider.url, "https://gitlab.work.com");
+        assert_eq!(provider.scope.owner.as_deref(), Some("platform"));
+        assert_eq!(provider.token, Some("user-token".to_string()));
+        assert_eq!(
+            config.source_of("providers.work-gitlab.owner"),
+            ValueSource::File(project.clone())
+        );
+        assert_eq!(
+            config.source_of("providers.work-gitlab.token"),
+            ValueSource::File(user.clone())
+        );
+        assert_eq!(config.files(), &[user, project]);
+    }
+
+    #[test]
+    fn test_project_file_cannot_redirect_token() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+        let user = dir.join("config.toml");
+        let project = dir.join(PROJECT_CONFIG_FILE);
+        fs::write(&user, "[providers.github]\ntoken = \"ghp_user\"\n").unwrap();
+        fs::write(
+            &project,
+            "[providers.github]\nurl = \"https://evil.example.com\"\n",
+        )
+        .unwrap();
+
+        let config = Config::load_layers(&[user.clone(), project.clone()]).unwrap();
+        assert!(matches!(
+            config.check_untrusted_urls(),
+            Err(ConfigError::UntrustedUrl { path, name }) if path == project && name == "github"
+        ));
+
+        // Included files are held to the same rule
+        let main = dir.join("main.toml");
+        fs::write(&main, "include = [\"config.toml\", \"redirect.toml\"]\n").unwrap();
+        fs::copy(&project, dir.join("redirect.toml")).unwrap();
+        let config = Config::load_layers(std::slice::from_ref(&main)).unwrap();
+        assert!(config.check_untrusted_urls().is_err());
+
+        // A URL that comes with its own token, or a later trusted URL, is fine
+        fs::write(
+            &project,
+            "[providers.github]\nurl = \"https://ghe.example.com\"\ntoken = \"ghp_project\"\n",
+        )
+        .unwrap();
+        let config = Config::load_layers(&[user.clone(), project.clone()]).unwrap();
+        assert!(config.check_untrusted_urls().is_ok());
+
+        fs::write(&main, "[providers.github]\nurl = \"https://github.com\"\n").unwrap();
+        fs::copy(dir.join("redirect.toml"), &project).unwrap();
+        let config = Config::load_layers(&[user, project, main]).unwrap();
+        assert!(config.check_untrusted_urls().is_ok());
+    }
+
+    #[test]
+    fn test_project_file_cannot_loosen_security() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+        let user = dir.join("config.toml");
+        let project = dir.join(PROJECT_CONFIG_FILE);
+        fs::write(&user, "[security]\nstrict = true\n").unwrap();
+        fs::write(
+            &project,
+            "[security]\nstrict = false\ntrusted_hosts = [\"evil.example.com\"]\n",
+        )
+        .unwrap();
+
+        let config = Config::load_layers(&[user.clone(), project.clone()]).unwrap();
+        assert_eq!(config.security.strict, Some(true));
+        assert_eq!(config.security.trusted_hosts, None);
+
+        // Tightening is fine
+        fs::write(&user, "[security]\nstrict = false\n").unwrap();
+        fs::write(&project, "[security]\nstrict = true\n").unwrap();
+        let config = Config::load_layers(&[user, project]).unwrap();
+        assert_eq!(config.security.strict, Some(true));
+    }
+
+    #[test]
+    fn test_includes_are_merged_before_including_file() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+        fs::create_dir_all(dir.join("team")).unwrap();
+        fs::write(
+            dir.join("team/shared.toml"),
+            r#"
+            [defaults]
+            limit = 3
+
+            [providers.ghe]
+            type = "github"
+            url = "https://ghe.team.com/api/v3"
+            "#,
+        )
+        .unwrap();
+        let main = dir.join("config.toml");
+        fs::write(
+            &main,
+            r#"
+            include = ["team/shared.toml"]
+
+            [defaults]
+            limit = 7
+            "#,
+        )
+        .unwrap();
+
+        let config = Config::load_layers(&[main]).unwrap();
+
+        assert_eq!(config.defaults.limit, Some(7));
+        assert_eq!(
+            config.resolve_provider("ghe").unwrap().url,
+            "https://ghe.team.com/api/v3"
+        );
+    }
+
+    #[test]
+    fn test_include_cycle_is_rejected() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+        let a = dir.join("a.toml");
+        fs::write(&a, "include = [\"b.toml\"]\n").unwrap();
+        fs::write(dir.join("b.toml"), "include = [\"a.toml\"]\n").unwrap();
+
+        let err = Config::load_layers(&[a]).unwrap_err().to_string();
+        assert!(err.contains("include cycle"), "{}", err);
+    }
+
+    #[test]
+    fn test_profile_overlays_defaults_and_providers() {
+        let toml = r#"
+            [defaults]
+            providers = ["github"]
+            limit = 5
+
+            [profiles.personal.providers.github]
+            token = "personal-token"
+
+            [profiles.work.defaults]
+            providers = ["work-gitlab"]
+
+            [profiles.work.providers.work-gitlab]
+            type = "gitlab"
+            url = "https://gitlab.work.com"
+            token = "work-token"
+        "#;
+
+        let mut work = Config::from_toml(toml).unwrap();
+        work.apply_profile("work").unwrap();
+        assert_eq!(work.active_profile(), Some("work"));
+        assert_eq!(work.default_providers(), vec!["work-gitlab".to_string()]);
+        assert_eq!(work.defaults.limit, Some(5));
+        assert_eq!(
+            work.resolve_provider("work-gitlab").unwrap().token,
+            Some("work-token".to_string())
+        );
+        assert!(work.resolve_provider("github").unwrap().token.is_none());
+        assert_eq!(
+            work.source_of("providers.work-gitlab.url"),
+            ValueSource::File(PathBuf::from("config.toml"))
+        );
+
+        let mut personal = Config::from_toml(toml).unwrap();
+        personal.apply_profile("personal").unwrap();
+        assert_eq!(personal.default_providers(), vec!["github".to_string()]);
+        assert_eq!(
+            personal.resolve_provider("github").unwrap().token,
+            Some("personal-token".to_string())
+        );
+        assert!(personal.resolve_provider("work-gitlab").is_none());
+    }
+
+    #[test]
+    fn test_profile_does_not_inherit_base_providers() {
+        let toml = r#"
+            [providers.github]
+            token = "personal-token"
+
+            [providers.work-gitlab]
+            type = "gitlab"
+            url = "https://gitlab.work.com"
+
+            [profiles.work.defaults]
+            providers = ["github", "work-gitlab"]
+
+            [profiles.shared]
+            inherit_providers = true
+
+            [profiles.shared.providers.github]
+            url = "https://ghe.work.com/api/v3"
+        "#;
+
+        let mut work = Config::from_toml(toml).unwrap();
+        work.apply_profile("work").unwrap();
+        assert!(!work.inherits_providers());
+        assert!(work.resolve_provider("github").unwrap().token.is_none());
+        assert!(work.resolve_provider("work-gitlab").is_none());
+        assert_eq!(
+            work.source_of("providers.github.token"),
+            ValueSource::Default
+        );
+
+        let mut shared = Config::from_toml(toml).unwrap();
+        shared.apply_profile("shared").unwrap();
+        assert!(shared.inherits_providers());
+        let github = shared.resolve_provider("github").unwrap();
+        assert_eq!(github.token.as_deref(), Some("personal-token"));
+        assert_eq!(github.url, "https://ghe.work.com/api/v3");
+        assert!(shared.resolve_provider("work-gitlab").is_some());
+    }
+
+    #[test]
+    fn test_unknown_profile_suggests_closest() {
+        let toml = r#"
+            [profiles.work.defaults]
+            limit = 3
+        "#;
+        let mut config = Config::from_toml(toml).unwrap();
+        let err = config.apply_profile("wrok").unwrap_err().to_string();
+        assert_eq!(err, "Unknown profile 'wrok'; did you mean 'work'?");
+    }
+
+    #[test]
+    fn test_expand_groups_and_all() {
+        let toml = r#"
+            [groups]
+            work = ["work-gitlab", "ghe", "github"]
+            everything = ["work", "personal"]
+            personal = ["github", "everything"]
+
+            [providers.work-gitlab]
+            type = "gitlab"
+            url = "https://gitlab.work.com"
+
+            [providers.ghe]
+            type = "github"
+            url = "https://ghe.work.com/api/v3"
+        "#;
+        let config = Config::from_toml(toml).unwrap();
+
+        assert_eq!(
+            config.expand_provider_names(&["work".to_string(), "gitlab".to_string()]),
+            vec!["work-gitlab", "ghe", "github", "gitlab"]
+        );
+        assert_eq!(
+            config.expand_provider_names(&["everything".to_string()]),
+            vec!["work-gitlab", "ghe", "github"]
+        );
+        assert_eq!(
+            config.expand_provider_names(&["ALL".to_string(), "github".to_string()]),
+            config.provider_names()
+        );
+        assert_eq!(config.group_names(), vec!["everything", "personal", "work"]);
+    }
+
+    #[test]
+    fn test_check_reports_unknown_group_members() {
+        let toml = r#"
+            [groups]
+            work = ["github", "work-gitlab"]
+        "#;
+        let config = Config::from_toml(toml).unwrap();
+        let checks = config.check();
+        let member = checks.iter().find(|c| c.name == "work-gitlab").unwrap();
+        assert!(member.has_errors());
+    }
+
+    #[test]
+    fn test_resolve_provider_scope() {
+        let toml = r#"
+            [providers.ghe]
+            type = "github"
+            url = "https://ghe.acme.com/api/v3"
+            owner = "acme"
+            include_archived = false
+            extra_query = "fork:true"
+            limit = 50
+            weight = 1.5
+        "#;
+        let config = Config::from_toml(toml).unwrap();
+
+        let scope = config.resolve_provider("ghe").unwrap().scope;
+        assert_eq!(scope.owner.as_deref(), Some("acme"));
+        assert_eq!(scope.include_archived, Some(false));
+        assert_eq!(scope.extra_query.as_deref(), Some("fork:true"));
+        assert_eq!(scope.limit, Some(50));
+        assert_eq!(scope.weight, Some(1.5));
+
+        let builtin = config.resolve_provider("github").unwrap().scope;
+        assert!(builtin.owner.is_none());
+        assert!(builtin.limit.is_none());
+    }
+
+    #[test]
+    fn test_stored_credential_used_when_no_token_configured() {
+        let toml = r#"
+            [providers.github]
+            token = "configured-token"
+
+            [providers.work-gitlab]
+            type = "gitlab"
+            url = "https://gitlab.work.com"
+            client_id = "app-id"
+        "#;
+        let mut config = Config::from_toml(toml).unwrap();
+        let credential = StoredCredential {
+            access_token: "oauth-token".to_string(),
+            refresh_token: Some("refresh".to_string()),
+            expires_at: Some(0),
+        };
+        let mut store = CredentialStore::default();
+        store.set("github", credential.clone());
+        store.set("work-gitlab", credential);
+        config.store = OnceLock::from(Ok(store));
+
+        let github = config.resolve_provider("github").unwrap();
+        assert_eq!(github.token, Some("configured-token".to_string()));
+        assert!(github.credential.is_none());
+
+        let gitlab = config.resolve_provider("work-gitlab").unwrap();
+        assert_eq!(gitlab.token, Some("oauth-token".to_string()));
+        assert_eq!(gitlab.oauth.client_id, Some("app-id".to_string()));
+        assert!(gitlab.credential.unwrap().is_expired());
+    }
+
+    #[test]
+    fn test_stored_credentials_are_kept_per_profile() {
+        let toml = r#"
+            [profiles.work.defaults]
+            limit = 5
+        "#;
+        let mut config = Config::from_toml(toml).unwrap();
+        let credential = |token: &str| StoredCredential {
+            access_token: token.to_string(),
+            refresh_token: None,
+            expires_at: None,
+        };
+        let mut store = CredentialStore::default();
+        store.set("github", credential("personal-token"));
+        store.set("work/gitlab", credential("work-token"));
+        config.store = OnceLock::from(Ok(store));
+
+        assert_eq!(
+            config.resolve_provider("github").unwrap().token.as_deref(),
+            Some("personal-token")
+        );
+        assert_eq!(config.resolve_provider("gitlab").unwrap().token, None);
+
+        config.apply_profile("work").unwrap();
+        assert_eq!(config.credential_key("gitlab"), "work/gitlab");
+        assert_eq!(config.resolve_provider("github").unwrap().token, None);
+        assert_eq!(
+            config.resolve_provider("gitlab").unwrap().token.as_deref(),
+            Some("work-token")
+        );
+    }
+
+    #[test]
+    fn test_parse_github_app() {
+        let toml = r#"
+            [providers.ghe]
+            type = "github"
+            url = "https://ghe.acme.com/api/v3"
+
+            [providers.ghe.github_app]
+            app_id = 12345
+            private_key_path = "/etc/repo_search/app.pem"
+        "#;
+        let config = Config::from_toml(toml).unwrap();
+
+        let app = config.resolve_provider("ghe").unwrap().github_app.unwrap();
+        assert_eq!(app.app_id, 12345);
+        assert_eq!(
+            app.private_key_path,
+            PathBuf::from("/etc/repo_search/app.pem")
+        );
+        assert!(app.installation_id.is_none());
+    }
 }
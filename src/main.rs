@@ -1,58 +1,80 @@
+mod auth;
 mod cli;
+mod commands;
 mod config;
+mod dedupe;
 mod models;
 mod output;
 mod providers;
-
-use std::collections::HashSet;
+mod query;
+mod rank;
+mod security;
 
 use anyhow::Result;
-use config::{Config, ProviderType, ResolvedProvider};
+use config::{Config, LoadOptions, ResolvedProvider, DEFAULT_LIMIT};
 use models::Repository;
-use providers::{BitbucketProvider, GitHubProvider, GitLabProvider, Provider};
-
-const DEFAULT_LIMIT: usize = 10;
+use providers::Affiliation;
+use query::{SearchQuery, Sort, SortKey};
 
 #[tokio::main]
 async fn main() -> Result<()> {
     let args = cli::parse();
-    let config = Config::load()?;
+    let options = LoadOptions {
+        config_file: args.config.clone(),
+        profile: args.profile.clone(),
+    };
+
+    if let Some(cli::Command::Config { action }) = &args.command {
+        return commands::config::run(action, &options).await;
+    }
+
+    let mut config = Config::load(&options)?;
+
+    if let Some(cli::Command::Completions { shell }) = &args.command {
+        commands::completions::run(*shell, &config);
+        return Ok(());
+    }
+    if let Some(cli::Command::Auth { action }) = &args.command {
+        return commands::auth::run(action, &mut config).await;
+    }
 
     // Handle --list-providers flag
     if args.list_providers {
+        let names = config.provider_names();
+        for error in providers::detect::detect_types(&mut config, &names).await {
+            eprintln!("Warning: {}", error);
+        }
+
         println!("Configured providers:");
         for name in config.provider_names() {
             if let Some(resolved) = config.resolve_provider(&name) {
-                let type_str = match resolved.provider_type {
-                    ProviderType::Github => "github",
-                    ProviderType::Gitlab => "gitlab",
-                    ProviderType::Bitbucket => "bitbucket",
-                };
-                let auth = if resolved.token.is_some() {
-                    " (authenticated)"
+                let type_str = resolved.provider_type.as_str();
+                let auth = if resolved.has_credentials() {
+                    " (token configured)"
                 } else {
                     ""
                 };
                 println!("  {} [{}] -> {}{}", name, type_str, resolved.url, auth);
             }
         }
-        return Ok(());
-    }
 
-    if args.mine && args.owner.is_some() {
-        eprintln!("Error: --owner and --mine cannot be used together");
-        std::process::exit(1);
+        let groups = config.group_names();
+        if !groups.is_empty() {
+            println!("\nGroups:");
+            for group in groups {
+                println!("  {} -> {}", group, config.groups[&group].join(", "));
+            }
+        }
+        return Ok(());
     }
 
-    // Resolve limit: CLI > config > default
-    let limit = args
-        .limit
-        .or(config.defaults.limit)
-        .unwrap_or(DEFAULT_LIMIT);
-
     // Require query for search
-    let query = match args.query {
-        Some(q) => q,
+    let mut query = match args.query.as_deref().map(SearchQuery::parse) {
+        Some(Ok(query)) => query,
+        Some(Err(e)) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
         None => {
             eprintln!("Error: Search query is required");
             eprintln!("Usage: repo_search <QUERY>");
@@ -60,16 +82,64 @@ async fn main() -> Result<()> {
         }
     };
 
+    query.sort = Sort {
+        key: args.sort.unwrap_or_default(),
+        order: args.order,
+    };
+    if let Err(e) = apply_filter_flags(&args, &mut query) {
+        eprintln!("Error: {}", e);
+        std::process::exit(1);
+    }
+
+    // `owner:` in the query works like --owner
+    let owner = match (args.owner.clone(), query.owner.take()) {
+        (Some(flag), Some(qualifier)) if !flag.eq_ignore_ascii_case(&qualifier) => {
+            eprintln!("Error: --owner and `owner:` in the query disagree");
+            std::process::exit(1);
+        }
+        (flag, qualifier) => flag.or(qualifier),
+    };
+    // Likewise `in:` and --in
+    if !args.fields.is_empty() {
+        if !query.fields.is_empty() && query.fields != args.fields {
+            eprintln!("Error: --in and `in:` in the query disagree");
+            std::process::exit(1);
+        }
+        query.fields = args.fields.clone();
+    }
+    if args.mine.is_some() && owner.is_some() {
+        eprintln!("Error: --owner and --mine cannot be used together");
+        std::process::exit(1);
+    }
+
     // Resolve which providers to search
     let provider_names = resolve_provider_names(&args.provider, &config);
+    for error in providers::detect::detect_types(&mut config, &provider_names).await {
+        eprintln!("Warning: {}", error);
+    }
 
     // Resolve provider configurations
+    let trusted_hosts = config.security.trusted_hosts.clone().unwrap_or_default();
     let mut resolved_providers: Vec<ResolvedProvider> = Vec::new();
     for name in &provider_names {
         match config.resolve_provider(name) {
             Some(mut resolved) => {
                 // Apply URL override from CLI if provided
                 if let Some(ref url) = args.url {
+                    // Never hand a token to a host it wasn't configured for by accident
+                    if resolved.has_credentials()
+                        && !args.trust_url
+                        && !security::may_send_token(&resolved.url, url, &trusted_hosts)
+                    {
+                        eprintln!(
+                            "Error: Refusing to send the {} token to {} (configured for {}); \
+                             pass --trust-url or add the host to security.trusted_hosts",
+                            name,
+                            security::host(url).unwrap_or_else(|| url.clone()),
+                            security::host(&resolved.url).unwrap_or_else(|| resolved.url.clone()),
+                        );
+                        std::process::exit(1);
+                    }
                     resolved.url = url.clone();
                 }
                 resolved_providers.push(resolved);
@@ -85,13 +155,27 @@ async fn main() -> Result<()> {
         std::process::exit(1);
     }
 
+    // Refresh expired tokens from `auth login` before searching
+    for warning in auth::refresh_expired(&mut resolved_providers, &config).await {
+        eprintln!("Warning: {}", warning);
+    }
+    for error in auth::apply_github_apps(&mut resolved_providers, owner.as_deref()).await {
+        eprintln!("Warning: {}", error);
+    }
+    if resolved_providers.is_empty() {
+        eprintln!("Error: No valid providers to search");
+        std::process::exit(1);
+    }
+
     // Execute searches
     let (repos, errors) = execute_searches(
         &resolved_providers,
         &query,
         args.mine,
-        args.owner.as_deref(),
-        limit,
+        owner.as_deref(),
+        args.limit,
+        config.defaults.limit,
+        args.total_limit.or(config.defaults.total_limit),
     )
     .await;
 
@@ -110,80 +194,222 @@ async fn main() -> Result<()> {
     Ok(())
 }
 
+/// Fold the filter flags into the query as the qualifiers they stand for
+fn apply_filter_flags(args: &cli::Args, query: &mut SearchQuery) -> Result<()> {
+    let flag = |set: bool, unset: bool| match (set, unset) {
+        (true, _) => Some("true"),
+        (_, true) => Some("false"),
+        _ => None,
+    };
+
+    let mut qualifiers: Vec<(&str, &str, String)> = Vec::new();
+    if let Some(language) = &args.language {
+        qualifiers.push(("--language", "language", language.clone()));
+    }
+    for topic in &args.topic {
+        qualifiers.push(("--topic", "topic", topic.clone()));
+    }
+    if let Some(stars) = args.min_stars {
+        qualifiers.push(("--min-stars", "stars", format!(">={}", stars)));
+    }
+    if let Some(date) = &args.updated_since {
+        qualifiers.push(("--updated-since", "updated", format!(">={}", date)));
+    }
+    if let Some(archived) = flag(args.archived, args.no_archived) {
+        qualifiers.push(("--archived", "archived", archived.to_string()));
+    }
+    if let Some(fork) = flag(args.forks, args.no_forks) {
+        qualifiers.push(("--forks", "fork", fork.to_string()));
+    }
+
+    for (name, key, value) in qualifiers {
+        query
+            .apply(key, &value)
+            .map_err(|e| anyhow::anyhow!("{}: {}", name, e))?;
+    }
+    Ok(())
+}
+
 fn resolve_provider_names(cli_providers: &[String], config: &Config) -> Vec<String> {
     if !cli_providers.is_empty() {
-        // Expand "all" to all configured providers
-        let mut names = HashSet::new();
-        for p in cli_providers {
-            if p.eq_ignore_ascii_case("all") {
-                for name in config.provider_names() {
-                    names.insert(name);
-                }
-            } else {
-                names.insert(p.clone());
-            }
-        }
-        return names.into_iter().collect();
+        // Expand "all" and groups to their providers, in the order given
+        return config.expand_provider_names(cli_providers);
     }
 
-    // Use config defaults
-    config.default_providers()
+    // Use config defaults, which may also name groups
+    config.expand_provider_names(&config.default_providers())
 }
 
 async fn execute_searches(
     providers: &[ResolvedProvider],
-    query: &str,
-    mine_only: bool,
+    query: &SearchQuery,
+    mine: Option<Affiliation>,
     owner: Option<&str>,
-    limit: usize,
+    limit_override: Option<usize>,
+    default_limit: Option<usize>,
+    total_limit: Option<usize>,
 ) -> (Vec<Repository>, Vec<String>) {
     use tokio::task::JoinSet;
 
     let mut join_set = JoinSet::new();
 
-    for provider in providers {
+    for (index, provider) in providers.iter().enumerate() {
         let name = provider.name.clone();
-        let url = provider.url.clone();
-        let token = provider.token.clone();
-        let provider_type = provider.provider_type;
-        let query = query.to_string();
+        let client = providers::build(provider);
+        let secrets: Vec<String> = provider.secrets().into_iter().map(String::from).collect();
+        let query = query.clone();
+
+        // Resolve limit: CLI > provider config > defaults config > default.
+        // With a total limit, fetch enough from each provider to fill it alone
+        // in case the others come back short or turn out to be mirrors.
+        let limit = limit_override
+            .or(provider.scope.limit)
+            .or(default_limit)
+            .unwrap_or(DEFAULT_LIMIT);
+        let limit = match total_limit {
+            Some(total) if limit_override.is_none() => limit.max(total),
+            _ => limit,
+        };
         let owner = owner.map(|value| value.to_string());
 
         join_set.spawn(async move {
-            let result: Result<Vec<Repository>> = match provider_type {
-                ProviderType::Github => {
-                    let p = GitHubProvider::new(url, token, name.clone());
-                    p.search(&query, mine_only, owner.as_deref(), limit).await
-                }
-                ProviderType::Gitlab => {
-                    let p = GitLabProvider::new(url, token, name.clone());
-                    p.search(&query, mine_only, owner.as_deref(), limit).await
-                }
-                ProviderType::Bitbucket => {
-                    let p = BitbucketProvider::new(url, token, name.clone());
-                    p.search(&query, mine_only, owner.as_deref(), limit).await
-                }
-            };
-            (name, result)
+            let result = client.search(&query, mine, owner.as_deref(), limit).await;
+            (index, name, secrets, result)
         });
     }
 
-    let mut all_repos = Vec::new();
-    let mut errors = Vec::new();
+    // Collect by provider position so the output doesn't depend on which
+    // request finished first
+    let mut results: Vec<Option<Result<Vec<Repository>, String>>> = vec![None; providers.len()];
+    let mut task_errors = Vec::new();
 
     while let Some(result) = join_set.join_next().await {
         match result {
-            Ok((_name, Ok(repos))) => {
-                all_repos.extend(repos);
+            Ok((index, _name, _secrets, Ok(repos))) => {
+                results[index] = Some(Ok(repos));
             }
-            Ok((name, Err(e))) => {
-                errors.push(format!("{}: {}", name, e));
+            Ok((index, name, secrets, Err(e))) => {
+                let secrets: Vec<&str> = secrets.iter().map(String::as_str).collect();
+                let error = security::redact(&format!("{}: {}", name, e), &secrets);
+                results[index] = Some(Err(error));
             }
             Err(e) => {
-                errors.push(format!("Task error: {}", e));
+                task_errors.push(format!("Task error: {}", e));
             }
         }
     }
 
+    let mut ranked = Vec::new();
+    let mut errors = Vec::new();
+    for (provider, result) in providers.iter().zip(results) {
+        match result {
+            Some(Ok(repos)) => ranked.push(rank::ProviderResults {
+                repos,
+                weight: provider.scope.weight.unwrap_or(1.0),
+            }),
+            Some(Err(error)) => errors.push(error),
+            None => {}
+        }
+    }
+    errors.extend(task_errors);
+
+    let mut all_repos = if query.sort.key == SortKey::Relevance {
+        rank::merge(query, ranked)
+    } else {
+        let mut repos: Vec<Repository> = ranked.into_iter().flat_map(|r| r.repos).collect();
+        query.sort.apply(&mut repos);
+        repos
+    };
+    // Mirrors count once towards the total
+    all_repos = dedupe::collapse(all_repos);
+    if let Some(total) = total_limit {
+        all_repos = rank::take_fair(all_repos, total);
+    }
+
     (all_repos, errors)
 }
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+
+    use super::*;
+    use query::{Comparison, Op};
+
+    fn folded(argv: &[&str]) -> Result<SearchQuery> {
+        let args = cli::Args::try_parse_from(argv)?;
+        let mut query = SearchQuery::parse(args.query.as_deref().unwrap_or_default())?;
+        apply_filter_flags(&args, &mut query)?;
+        Ok(query)
+    }
+
+    #[test]
+    fn test_query_named_like_a_command() {
+        let args = cli::Args::try_parse_from(["repo_search", "--", "auth"]).unwrap();
+        assert_eq!(args.query.as_deref(), Some("auth"));
+        assert!(args.command.is_none());
+
+        // A lone command name is still a search, as it was before the commands
+        for (argv, query) in [
+            (&["repo_search", "auth"][..], "auth"),
+            (&["repo_search", "config", "-p", "github"], "config"),
+            (&["repo_search", "--json", "completions"], "completions"),
+        ] {
+            let args = cli::parse_from(argv).unwrap();
+            assert_eq!(args.query.as_deref(), Some(query));
+            assert!(args.command.is_none());
+        }
+
+        let args = cli::parse_from(["repo_search", "config", "path"]).unwrap();
+        assert!(args.command.is_some());
+        assert!(cli::parse_from(["repo_search", "auth", "login"]).is_err());
+    }
+
+    #[test]
+    fn test_filter_flags_become_qualifiers() {
+        let query = folded(&[
+            "repo_search",
+            "--language",
+            "rust",
+            "--topic",
+            "cli",
+            "--topic",
+            "parser",
+            "--min-stars",
+            "10",
+            "--updated-since",
+            "2025-01-01",
+            "--no-archived",
+            "--forks",
+            "billing",
+        ])
+        .unwrap();
+
+        assert_eq!(query.text, "billing");
+        assert_eq!(query.language.as_deref(), Some("rust"));
+        assert_eq!(query.topics, vec!["cli", "parser"]);
+        assert_eq!(
+            query.stars,
+            Some(Comparison {
+                op: Op::Ge,
+                value: 10
+            })
+        );
+        assert_eq!(query.updated.unwrap().to_string(), ">=2025-01-01");
+        assert_eq!(query.archived, Some(false));
+        assert_eq!(query.fork, Some(true));
+    }
+
+    #[test]
+    fn test_filter_flag_conflicts_with_qualifier() {
+        let err = folded(&["repo_search", "--language", "go", "billing lang:rust"]).unwrap_err();
+        let repeated = query::QueryError::Repeated {
+            key: "language".to_string(),
+        };
+        assert_eq!(err.to_string(), format!("--language: {}", repeated));
+
+        // Topics add up instead of conflicting
+        let query = folded(&["repo_search", "--topic", "cli", "topic:parser"]).unwrap();
+        assert_eq!(query.topics, vec!["parser", "cli"]);
+    }
+}
@@ -1,7 +1,7 @@
 use serde::Serialize;
 use tabled::Tabled;
 
-#[derive(Debug, Clone, Serialize, Tabled)]
+#[derive(Debug, Clone, Default, Serialize, Tabled)]
 pub struct Repository {
     #[tabled(rename = "Name")]
     pub name: String,
@@ -14,10 +14,47 @@ pub struct Repository {
     pub provider: String,
     #[tabled(rename = "URL")]
     pub url: String,
+    /// The same repository on other providers, e.g. mirrors
+    #[tabled(rename = "Also at")]
+    #[tabled(display_with = "display_locations")]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub also_at: Vec<Location>,
     #[tabled(skip)]
     pub full_name: String,
     #[tabled(skip)]
     pub description: Option<String>,
+
+    // Metadata used by query filters; `None` when the provider doesn't report it
+    #[tabled(skip)]
+    pub language: Option<String>,
+    #[tabled(skip)]
+    pub topics: Option<Vec<String>>,
+    #[tabled(skip)]
+    pub stars: Option<u64>,
+    #[tabled(skip)]
+    pub archived: Option<bool>,
+    #[tabled(skip)]
+    pub fork: Option<bool>,
+    #[tabled(skip)]
+    pub updated_at: Option<String>,
+    #[tabled(skip)]
+    pub created_at: Option<String>,
+
+    // Used to spot the same repository on several providers
+    #[tabled(skip)]
+    #[serde(skip)]
+    pub clone_url: Option<String>,
+    /// Where a mirror or import was copied from
+    #[tabled(skip)]
+    #[serde(skip)]
+    pub mirror_of: Option<String>,
+}
+
+/// Another place a repository lives
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Location {
+    pub provider: String,
+    pub url: String,
 }
 
 fn display_bool(b: &bool) -> String {
@@ -28,6 +65,14 @@ fn display_bool(b: &bool) -> String {
     }
 }
 
+fn display_locations(locations: &[Location]) -> String {
+    locations
+        .iter()
+        .map(|location| format!("{}: {}", location.provider, location.url))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
 #[derive(Debug, Clone, Serialize)]
 pub struct SearchResults {
     pub repositories: Vec<Repository>,
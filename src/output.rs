@@ -1,4 +1,7 @@
-use tabled::{settings::Style, Table};
+use tabled::{
+    settings::{locator::ByColumnName, Disable, Style},
+    Table,
+};
 
 use crate::models::{Repository, SearchResults};
 
@@ -27,7 +30,13 @@ pub fn print_results(repos: Vec<Repository>, as_json: bool) {
         println!("{}", serde_json::to_string_pretty(&results).unwrap());
     } else {
         println!("Found {} repositories:\n", repos.len());
-        let table = Table::new(&repos).with(Style::rounded()).to_string();
+        let mut table = Table::new(&repos);
+        table.with(Style::rounded());
+        // Only show the column when something was collapsed
+        if repos.iter().all(|repo| repo.also_at.is_empty()) {
+            table.with(Disable::column(ByColumnName::new("Also at")));
+        }
+        let table = table.to_string();
         println!("{}", table);
     }
 }
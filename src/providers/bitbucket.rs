@@ -3,19 +3,24 @@ use async_trait::async_trait;
 use reqwest::Client;
 use serde::Deserialize;
 
-use super::Provider;
+use super::{header_list, header_u64, page_size, Affiliation, Provider, TokenInfo, MAX_PAGES};
+use crate::config::ProviderScope;
 use crate::models::Repository;
+use crate::query::{next_day, Op, SearchField, SearchQuery, Sort, SortKey, SortOrder};
 
 pub struct BitbucketProvider {
     client: Client,
     base_url: String,
     token: Option<String>,
     display_name: String,
+    scope: ProviderScope,
 }
 
 #[derive(Debug, Deserialize)]
 struct BitbucketResponse {
     values: Vec<BitbucketRepo>,
+    /// URL of the next page, absent on the last one
+    next: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -26,11 +31,24 @@ struct BitbucketRepo {
     is_private: bool,
     links: BitbucketLinks,
     owner: BitbucketOwner,
+    #[serde(default)]
+    language: String,
+    updated_on: Option<String>,
+    created_on: Option<String>,
+    parent: Option<serde_json::Value>,
 }
 
 #[derive(Debug, Deserialize)]
 struct BitbucketLinks {
     html: BitbucketLink,
+    #[serde(default)]
+    clone: Vec<BitbucketCloneLink>,
+}
+
+#[derive(Debug, Deserialize)]
+struct BitbucketCloneLink {
+    name: String,
+    href: String,
 }
 
 #[derive(Debug, Deserialize)]
@@ -55,9 +73,16 @@ impl BitbucketProvider {
             base_url,
             token,
             display_name,
+            scope: ProviderScope::default(),
         }
     }
 
+    /// Apply per-provider defaults from the config
+    pub fn with_scope(mut self, scope: ProviderScope) -> Self {
+        self.scope = scope;
+        self
+    }
+
     fn build_request(&self, url: &str) -> reqwest::RequestBuilder {
         let mut request = self.client.get(url).header("User-Agent", "repo_search_cli");
 
@@ -68,28 +93,32 @@ impl BitbucketProvider {
         request
     }
 
-    async fn get_username(&self) -> Result<String> {
-        let token = self
-            .token
-            .as_ref()
-            .ok_or_else(|| anyhow::anyhow!("Authentication required to get username"))?;
-
-        let url = format!("{}/user", self.base_url);
-        let response = self
-            .client
-            .get(&url)
-            .header("Authorization", format!("Bearer {}", token))
-            .header("User-Agent", "repo_search_cli")
-            .send()
-            .await
-            .context("Failed to fetch Bitbucket user")?;
-
-        if !response.status().is_success() {
-            anyhow::bail!("Bitbucket API error: {}", response.status());
+    fn repository(&self, repo: BitbucketRepo) -> Repository {
+        Repository {
+            clone_url: repo
+                .links
+                .clone
+                .into_iter()
+                .find(|link| link.name == "https")
+                .map(|link| link.href),
+            name: repo.name,
+            full_name: repo.full_name,
+            description: repo.description,
+            url: repo.links.html.href,
+            private: repo.is_private,
+            provider: self.display_name.clone(),
+            owner: repo.owner.display_name,
+            language: Some(repo.language).filter(|language| !language.is_empty()),
+            topics: None,
+            stars: None,
+            // Bitbucket Cloud cannot archive repositories
+            archived: Some(false),
+            fork: Some(repo.parent.is_some()),
+            updated_at: repo.updated_on,
+            created_at: repo.created_on,
+            also_at: Vec::new(),
+            mirror_of: None,
         }
-
-        let user: BitbucketUser = response.json().await?;
-        Ok(user.username)
     }
 }
 
@@ -97,76 +126,152 @@ impl BitbucketProvider {
 impl Provider for BitbucketProvider {
     async fn search(
         &self,
-        query: &str,
-        mine_only: bool,
+        query: &SearchQuery,
+        mine: Option<Affiliation>,
         owner: Option<&str>,
         limit: usize,
     ) -> Result<Vec<Repository>> {
+        let owner = owner.or(self.scope.owner.as_deref());
+
+        // Bitbucket repositories have neither topics nor stars
+        if !query.topics.is_empty() {
+            anyhow::bail!("Bitbucket has no repository topics; `topic:` is not supported");
+        }
+        if query.stars.is_some() {
+            anyhow::bail!("Bitbucket has no stars; `stars:` is not supported");
+        }
+        if query.fields.contains(&SearchField::Readme) {
+            anyhow::bail!("Bitbucket can't search README contents; `in:readme` is not supported");
+        }
+        if query.fields == [SearchField::Topics] {
+            anyhow::bail!("Bitbucket has no repository topics; `in:topics` is not supported");
+        }
+        super::skip_native("Bitbucket", query);
+
         // Bitbucket requires authentication for searching all repositories
-        // Without auth, we can only search within a specific user's repos
-        if !mine_only && owner.is_none() && self.token.is_none() {
-            anyhow::bail!("Bitbucket requires authentication to search all repositories. Set BITBUCKET_TOKEN or use --mine flag.");
+        // and for `role=`; without it, only a workspace's public repos are listed
+        if self.token.is_none() {
+            if mine.is_some() {
+                anyhow::bail!("Authentication required to list your Bitbucket repositories. Set BITBUCKET_TOKEN.");
+            }
+            if owner.is_none() {
+                anyhow::bail!("Bitbucket requires authentication to search all repositories. Set BITBUCKET_TOKEN or use --owner.");
+            }
+        }
+
+        let mut bbql = bbql(query);
+        if let Some(extra) = &self.scope.extra_query {
+            bbql.raw(extra);
         }
 
-        let url = if mine_only {
-            let username = self.get_username().await?;
+        let mut url = if let Some(affiliation) = mine {
+            let Some(role) = role(affiliation) else {
+                anyhow::bail!(
+                    "Bitbucket has no {} repositories; `--mine={}` is not supported",
+                    affiliation.as_str(),
+                    affiliation.as_str()
+                );
+            };
             format!(
-                "{}/repositories/{}?q=name~\"{}\"&pagelen={}",
+                "{}/repositories?role={}&pagelen={}",
                 self.base_url,
-                username,
-                urlencoding::encode(query),
-                limit
+                role,
+                page_size(limit)
             )
         } else if let Some(owner) = owner {
             format!(
-                "{}/repositories/{}?q=name~\"{}\"&pagelen={}",
+                "{}/repositories/{}?pagelen={}",
                 self.base_url,
-                owner,
-                urlencoding::encode(query),
-                limit
+                urlencoding::encode(owner),
+                page_size(limit)
             )
         } else {
             format!(
-                "{}/repositories?q=name~\"{}\"&pagelen={}",
+                "{}/repositories?pagelen={}",
                 self.base_url,
-                urlencoding::encode(query),
-                limit
+                page_size(limit)
             )
         };
+        if let Some(bbql) = bbql.build() {
+            url.push_str(&format!("&q={}", urlencoding::encode(&bbql)));
+        }
+        if let Some(sort) = sort_field(&query.sort) {
+            url.push_str(&format!("&sort={}", urlencoding::encode(&sort)));
+        }
+
+        let mut repos = Vec::new();
+        let mut next = Some(url);
+        for _ in 0..MAX_PAGES {
+            let Some(url) = next.take() else { break };
+            let response = self
+                .build_request(&url)
+                .send()
+                .await
+                .context("Failed to search Bitbucket repositories")?;
+
+            if !response.status().is_success() {
+                let status = response.status();
+                let body = response.text().await.unwrap_or_default();
+                return Err(super::api_error(
+                    "Bitbucket",
+                    status,
+                    &body,
+                    self.token.as_deref(),
+                ));
+            }
+
+            let page: BitbucketResponse = response
+                .json()
+                .await
+                .context("Failed to parse Bitbucket response")?;
+
+            repos.extend(
+                page.values
+                    .into_iter()
+                    .map(|repo| self.repository(repo))
+                    .filter(|repo| query.matches(repo)),
+            );
+            if repos.len() >= limit {
+                break;
+            }
+            next = page.next;
+        }
+        repos.truncate(limit);
+        Ok(repos)
+    }
 
+    async fn token_info(&self) -> Result<TokenInfo> {
+        let url = format!("{}/user", self.base_url);
         let response = self
             .build_request(&url)
             .send()
             .await
-            .context("Failed to search Bitbucket repositories")?;
+            .context("Failed to fetch Bitbucket user")?;
 
         if !response.status().is_success() {
-            let status = response.status();
-            let body = response.text().await.unwrap_or_default();
-            anyhow::bail!("Bitbucket API error ({}): {}", status, body);
+            anyhow::bail!("Bitbucket API error: {}", response.status());
         }
 
-        let bitbucket_response: BitbucketResponse = response
-            .json()
-            .await
-            .context("Failed to parse Bitbucket response")?;
-
-        let display_name = self.display_name.clone();
-        let repos = bitbucket_response
-            .values
-            .into_iter()
-            .map(|repo| Repository {
-                name: repo.name,
-                full_name: repo.full_name,
-                description: repo.description,
-                url: repo.links.html.href,
-                private: repo.is_private,
-                provider: display_name.clone(),
-                owner: repo.owner.display_name,
-            })
-            .collect();
+        let headers = response.headers().clone();
+        let user: BitbucketUser = response.json().await?;
 
-        Ok(repos)
+        let scopes = header_list(&headers, "x-oauth-scopes");
+        let mut warnings = Vec::new();
+        if let Some(scopes) = &scopes {
+            if !scopes.iter().any(|scope| scope.starts_with("repository")) {
+                warnings.push(
+                    "token lacks the `repository` scope; repository searches will fail".to_string(),
+                );
+            }
+        }
+
+        Ok(TokenInfo {
+            username: user.username,
+            scopes,
+            expires_at: None,
+            rate_limit_remaining: header_u64(&headers, "x-ratelimit-remaining"),
+            warnings,
+        })
     }
 
     fn name(&self) -> &'static str {
@@ -177,3 +282,205 @@ impl Provider for BitbucketProvider {
         self.token.is_some()
     }
 }
+
+/// Builds a BBQL filter, escaping every value so user input can't end the
+/// string literal and inject clauses of its own
+#[derive(Debug, Default)]
+struct Bbql {
+    clauses: Vec<String>,
+}
+
+impl Bbql {
+    /// `field="value"`
+    fn equals(&mut self, field: &str, value: &str) -> &mut Self {
+        self.clauses.push(format!("{}={}", field, literal(value)));
+        self
+    }
+
+    /// `field>value` for dates; `value` must already be a valid date
+    fn compare(&mut self, field: &str, op: Op, value: &str) -> &mut Self {
+        self.clauses.push(format!("{}{}{}", field, op, value));
+        self
+    }
+
+    /// `field~"value"` (case-insensitive substring) for any of the fields
+    fn contains_any(&mut self, fields: &[&str], value: &str) -> &mut Self {
+        let any: Vec<String> = fields
+            .iter()
+            .map(|field| format!("{}~{}", field, literal(value)))
+            .collect();
+        self.clauses.push(format!("({})", any.join(" OR ")));
+        self
+    }
+
+    /// A trusted clause from the config, e.g. `extra_query`
+    fn raw(&mut self, clause: &str) -> &mut Self {
+        self.clauses.push(format!("({})", clause));
+        self
+    }
+
+    fn build(&self) -> Option<String> {
+        if self.clauses.is_empty() {
+            return None;
+        }
+        Some(self.clauses.join(" AND "))
+    }
+}
+
+/// A BBQL string literal
+fn literal(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// Translate the query into BBQL. Forks are left to the client-side filter.
+fn bbql(query: &SearchQuery) -> Bbql {
+    let mut bbql = Bbql::default();
+    if !query.text.is_empty() {
+        let mut fields: Vec<&str> = query
+            .fields
+            .iter()
+            .filter_map(|field| match field {
+                SearchField::Name => Some("name"),
+                SearchField::Description => Some("description"),
+                SearchField::Topics | SearchField::Readme => None,
+            })
+            .collect();
+        if fields.is_empty() {
+            fields = vec!["name", "description"];
+        }
+        // Each word on its own, as the client-side text match does
+        for word in query.text.split_whitespace() {
+            bbql.contains_any(&fields, word);
+        }
+    }
+    if let Some(project) = &query.project {
+        bbql.equals("project.key", &project.to_ascii_uppercase());
+    }
+    if let Some(language) = &query.language {
+        bbql.equals("language", &language.to_ascii_lowercase());
+    }
+    if let Some(updated) = &query.updated {
+        // `updated_on` is a timestamp; a date covers the whole day up to the next midnight
+        let start = updated.value.clone();
+        let end = next_day(&updated.value);
+        match updated.op {
+            Op::Ge => bbql.compare("updated_on", Op::Ge, &start),
+            Op::Gt => bbql.compare("updated_on", Op::Ge, &end),
+            Op::Lt => bbql.compare("updated_on", Op::Lt, &start),
+            Op::Le => bbql.compare("updated_on", Op::Lt, &end),
+            Op::Eq => {
+                bbql.compare("updated_on", Op::Ge, &start)
+                    .compare("updated_on", Op::Lt, &end)
+            }
+        };
+    }
+    bbql
+}
+
+/// The `role` filter listing the user's repositories of one affiliation;
+/// Bitbucket has no stars or watches
+fn role(affiliation: Affiliation) -> Option<&'static str> {
+    match affiliation {
+        Affiliation::Owned => Some("owner"),
+        Affiliation::Collaborator => Some("contributor"),
+        Affiliation::Member | Affiliation::Any => Some("member"),
+        Affiliation::Starred | Affiliation::Watched => None,
+    }
+}
+
+/// Bitbucket's `sort` parameter; a leading `-` sorts descending. Stars and
+/// relevance have no field and are left to the merge.
+fn sort_field(sort: &Sort) -> Option<String> {
+    let field = match sort.key {
+        SortKey::Relevance | SortKey::Stars => return None,
+        SortKey::Updated => "updated_on",
+        SortKey::Name => "name",
+        SortKey::Created => "created_on",
+    };
+    Some(match sort.order() {
+        SortOrder::Asc => field.to_string(),
+        SortOrder::Desc => format!("-{}", field),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bbql() {
+        let query =
+            SearchQuery::parse("billing lang:Rust project:ops updated:>=2025-01-01 -fork").unwrap();
+        assert_eq!(
+            bbql(&query).build().unwrap(),
+            "(name~\"billing\" OR description~\"billing\") AND project.key=\"OPS\" \
+             AND language=\"rust\" AND updated_on>=2025-01-01"
+        );
+        assert_eq!(
+            bbql(&SearchQuery::parse("lang:go").unwrap())
+                .build()
+                .unwrap(),
+            "language=\"go\""
+        );
+        assert_eq!(
+            bbql(&SearchQuery::parse("billing in:name,topics").unwrap())
+                .build()
+                .unwrap(),
+            "(name~\"billing\")"
+        );
+        assert_eq!(
+            bbql(&SearchQuery::parse("billing api in:name").unwrap())
+                .build()
+                .unwrap(),
+            "(name~\"billing\") AND (name~\"api\")"
+        );
+    }
+
+    #[test]
+    fn test_bbql_updated_covers_whole_days() {
+        let clause = |updated: &str| {
+            let query = SearchQuery::parse(&format!("updated:{}", updated)).unwrap();
+            bbql(&query).build().unwrap()
+        };
+        assert_eq!(clause(">=2025-01-01"), "updated_on>=2025-01-01");
+        assert_eq!(clause(">2025-01-01"), "updated_on>=2025-01-02");
+        assert_eq!(clause("<2025-01-01"), "updated_on<2025-01-01");
+        assert_eq!(clause("<=2024-12-31"), "updated_on<2025-01-01");
+        assert_eq!(
+            clause("2024-02-28"),
+            "updated_on>=2024-02-28 AND updated_on<2024-02-29"
+        );
+    }
+
+    #[test]
+    fn test_sort_field() {
+        let sort = |key, order| sort_field(&Sort { key, order });
+        assert_eq!(sort(SortKey::Updated, None).as_deref(), Some("-updated_on"));
+        assert_eq!(sort(SortKey::Name, None).as_deref(), Some("name"));
+        assert_eq!(
+            sort(SortKey::Created, Some(SortOrder::Asc)).as_deref(),
+            Some("created_on")
+        );
+        assert_eq!(sort(SortKey::Stars, None), None);
+    }
+
+    #[test]
+    fn test_role() {
+        assert_eq!(role(Affiliation::Owned), Some("owner"));
+        assert_eq!(role(Affiliation::Collaborator), Some("contributor"));
+        assert_eq!(role(Affiliation::Any), Some("member"));
+        assert_eq!(role(Affiliation::Starred), None);
+    }
+
+    #[test]
+    fn test_bbql_escapes_values() {
+        let query = SearchQuery {
+            text: r#"foo"||name~"\"#.to_string(),
+            ..SearchQuery::default()
+        };
+        assert_eq!(
+            bbql(&query).build().unwrap(),
+            r#"(name~"foo\"||name~\"\\" OR description~"foo\"||name~\"\\")"#
+        );
+    }
+}
@@ -0,0 +1,323 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use anyhow::Result;
+use reqwest::{Client, StatusCode};
+use serde::{Deserialize, Serialize};
+
+use crate::auth::credentials::now;
+use crate::config::{Config, ProviderType};
+use crate::security;
+
+/// Each probe gives up quickly so a dead host doesn't stall the search
+const PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How long a failed detection is remembered before the host is probed again
+const FAILURE_TTL_SECS: u64 = 3600;
+
+/// A provider type found by probing, with the URL the provider should use
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Detected {
+    #[serde(rename = "type")]
+    pub provider_type: ProviderType,
+    pub url: String,
+}
+
+/// Detected types cached on disk, keyed by the configured URL
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct DetectionCache {
+    #[serde(default)]
+    urls: BTreeMap<String, Detected>,
+    /// Recent failures, so every run doesn't wait on the same probes
+    #[serde(default)]
+    failures: BTreeMap<String, Failure>,
+}
+
+/// Why detection failed for a URL, and when
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Failure {
+    message: String,
+    /// Seconds since the Unix epoch
+    at: u64,
+}
+
+impl DetectionCache {
+    fn path() -> Option<PathBuf> {
+        dirs::cache_dir().map(|dir| dir.join("repo_search").join("provider_types.toml"))
+    }
+
+    pub fn load() -> Self {
+        Self::path()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .and_then(|content| toml::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    fn save(&self) -> Result<()> {
+        let Some(path) = Self::path() else {
+            return Ok(());
+        };
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&path, toml::to_string(self)?)?;
+        Ok(())
+    }
+
+    pub fn get(&self, url: &str) -> Option<&Detected> {
+        self.urls.get(url)
+    }
+
+    /// The message of a failure for `url` recorded within `FAILURE_TTL_SECS` of `now`
+    fn recent_failure(&self, url: &str, now: u64) -> Option<&str> {
+        self.failures
+            .get(url)
+            .filter(|failure| failure.at + FAILURE_TTL_SECS > now)
+            .map(|failure| failure.message.as_str())
+    }
+}
+
+/// What a probe endpoint says about the host
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Probe {
+    GitLab,
+    GitHubEnterprise,
+    Gitea,
+    BitbucketServer,
+}
+
+impl Probe {
+    const ALL: [Probe; 4] = [
+        Probe::GitLab,
+        Probe::GitHubEnterprise,
+        Probe::Gitea,
+        Probe::BitbucketServer,
+    ];
+
+    fn path(&self) -> &'static str {
+        match self {
+            Probe::GitLab => "/api/v4/version",
+            Probe::GitHubEnterprise => "/api/v3/meta",
+            Probe::Gitea => "/api/v1/version",
+            Probe::BitbucketServer => "/rest/api/1.0/application-properties",
+        }
+    }
+
+    /// Whether the response to this probe identifies the product
+    fn matches(&self, status: StatusCode, body: &str) -> bool {
+        let json: Option<serde_json::Value> = serde_json::from_str(body).ok();
+        let has_key = |key: &str| json.as_ref().is_some_and(|json| json.get(key).is_some());
+
+        match self {
+            // /version needs a token; an anonymous probe still gets GitLab's 401 body
+            Probe::GitLab => {
+                (status.is_success() && has_key("version") && has_key("revision"))
+                    || (status == StatusCode::UNAUTHORIZED && body.contains("401 Unauthorized"))
+            }
+            Probe::GitHubEnterprise => {
+                status.is_success()
+                    && (has_key("installed_version")
+                        || has_key("verifiable_password_authentication"))
+            }
+            Probe::Gitea => status.is_success() && has_key("version") && !has_key("revision"),
+            Probe::BitbucketServer => {
+                status.is_success()
+                    && json
+                        .as_ref()
+                        .and_then(|json| json.get("displayName"))
+                        .and_then(|name| name.as_str())
+                        == Some("Bitbucket")
+            }
+        }
+    }
+}
+
+/// Probe `url` for entries of `config` that have a URL but no type, fill in
+/// what is found, and cache it. Returns an error message per failed provider.
+pub async fn detect_types(config: &mut Config, names: &[String]) -> Vec<String> {
+    let pending = config.undetected_providers(names);
+    if pending.is_empty() {
+        return Vec::new();
+    }
+
+    let client = match Client::builder().timeout(PROBE_TIMEOUT).build() {
+        Ok(client) => client,
+        Err(e) => return vec![format!("Failed to build HTTP client: {}", e)],
+    };
+    let mut cache = DetectionCache::load();
+    let mut errors = Vec::new();
+
+    let now = now();
+    cache
+        .failures
+        .retain(|_, failure| failure.at + FAILURE_TTL_SECS > now);
+    for (name, url, token) in pending {
+        if let Some(message) = cache.recent_failure(&url, now) {
+            errors.push(format!(
+                "{}: {} (not probing again for an hour)",
+                name, message
+            ));
+            continue;
+        }
+        let detected = match cache.get(&url) {
+            Some(detected) => Ok(detected.clone()),
+            None => detect(&client, &url, token.as_deref()).await,
+        };
+        match detected {
+            Ok(detected) => {
+                cache.failures.remove(&url);
+                cache.urls.insert(url, detected.clone());
+                config.set_detected_type(&name, &detected);
+            }
+            Err(e) => {
+                let secrets: Vec<&str> = token.as_deref().into_iter().collect();
+                let message = security::redact(&e.to_string(), &secrets);
+                errors.push(format!("{}: {}", name, message));
+                cache.failures.insert(url, Failure { message, at: now });
+            }
+        }
+    }
+
+    // A failed cache write only costs another probe next time
+    let _ = cache.save();
+    errors
+}
+
+/// Work out which product serves `url`
+async fn detect(client: &Client, url: &str, token: Option<&str>) -> Result<Detected> {
+    if let Some(detected) = known_host(url) {
+        return Ok(detected);
+    }
+
+    let base = base_url(url);
+    for probe in Probe::ALL {
+        let mut request = client
+            .get(format!("{}{}", base, probe.path()))
+            .header("User-Agent", "repo_search_cli")
+            .header("Accept", "application/json");
+        // The token only ever goes to the host it was configured for
+        if let Some(token) = token {
+            request = request.header("Authorization", format!("Bearer {}", token));
+        }
+        let Ok(response) = request.send().await else {
+            continue;
+        };
+        let status = response.status();
+        let body = response.text().await.unwrap_or_default();
+        if !probe.matches(status, &body) {
+            continue;
+        }
+
+        return match probe {
+            Probe::GitLab => Ok(Detected {
+                provider_type: ProviderType::Gitlab,
+                url: base,
+            }),
+            Probe::GitHubEnterprise => Ok(Detected {
+                provider_type: ProviderType::Github,
+                url: format!("{}/api/v3", base),
+            }),
+            Probe::Gitea => anyhow::bail!("{} looks like Gitea, which is not supported", base),
+            Probe::BitbucketServer => anyhow::bail!(
+                "{} looks like Bitbucket Server, which is not supported (only Bitbucket Cloud is)",
+                base
+            ),
+        };
+    }
+
+    anyhow::bail!(
+        "could not detect the provider type of {}; set `type` to github, gitlab or bitbucket",
+        url
+    )
+}
+
+/// Public hosted services, recognised without probing
+fn known_host(url: &str) -> Option<Detected> {
+    let provider_type = match security::host(url)?.as_str() {
+        "github.com" | "api.github.com" => ProviderType::Github,
+        "gitlab.com" => ProviderType::Gitlab,
+        "bitbucket.org" | "api.bitbucket.org" => ProviderType::Bitbucket,
+        _ => return None,
+    };
+    Some(Detected {
+        provider_type,
+        url: provider_type.default_url().to_string(),
+    })
+}
+
+/// Strip API path suffixes so probes start from the instance root
+fn base_url(url: &str) -> String {
+    let mut base = url.trim_end_matches('/');
+    for suffix in ["/api/v3", "/api/v4", "/api/v1", "/rest/api/1.0"] {
+        if let Some(stripped) = base.strip_suffix(suffix) {
+            base = stripped;
+            break;
+        }
+    }
+    base.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_failures_expire() {
+        let mut cache = DetectionCache::default();
+        cache.failures.insert(
+            "https://git.acme.com".to_string(),
+            Failure {
+                message: "could not detect".to_string(),
+                at: 1000,
+            },
+        );
+        assert_eq!(
+            cache.recent_failure("https://git.acme.com", 1000 + 60),
+            Some("could not detect")
+        );
+        assert_eq!(
+            cache.recent_failure("https://git.acme.com", 1000 + FAILURE_TTL_SECS),
+            None
+        );
+        assert_eq!(cache.recent_failure("https://other.acme.com", 1000), None);
+    }
+
+    #[test]
+    fn test_base_url_and_known_hosts() {
+        assert_eq!(
+            base_url("https://ghe.acme.com/api/v3/"),
+            "https://ghe.acme.com"
+        );
+        assert_eq!(
+            base_url("https://git.acme.com/gitlab"),
+            "https://git.acme.com/gitlab"
+        );
+        assert_eq!(
+            known_host("https://github.com").unwrap().url,
+            "https://api.github.com"
+        );
+        assert!(known_host("https://ghe.acme.com").is_none());
+    }
+
+    #[test]
+    fn test_probe_matches() {
+        assert!(Probe::GitLab.matches(StatusCode::OK, r#"{"version":"16.9.1","revision":"abc"}"#));
+        assert!(Probe::GitLab.matches(
+            StatusCode::UNAUTHORIZED,
+            r#"{"message":"401 Unauthorized"}"#
+        ));
+        assert!(!Probe::GitLab.matches(StatusCode::NOT_FOUND, "Not Found"));
+        assert!(Probe::GitHubEnterprise.matches(
+            StatusCode::OK,
+            r#"{"verifiable_password_authentication":true,"installed_version":"3.12.0"}"#
+        ));
+        assert!(Probe::Gitea.matches(StatusCode::OK, r#"{"version":"1.21.0"}"#));
+        assert!(Probe::BitbucketServer.matches(
+            StatusCode::OK,
+            r#"{"version":"8.9.0","displayName":"Bitbucket"}"#
+        ));
+        assert!(!Probe::BitbucketServer.matches(StatusCode::OK, r#"{"displayName":"Jira"}"#));
+    }
+}
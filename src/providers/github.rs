@@ -3,14 +3,20 @@ use async_trait::async_trait;
 use reqwest::Client;
 use serde::Deserialize;
 
-use super::Provider;
+use super::{
+    header_list, header_u64, next_link, page_size, Affiliation, Provider, TokenInfo, MAX_PAGES,
+    PAGE_SIZE,
+};
+use crate::config::ProviderScope;
 use crate::models::Repository;
+use crate::query::{SearchField, SearchQuery, SortKey};
 
 pub struct GitHubProvider {
     client: Client,
     base_url: String,
     token: Option<String>,
     display_name: String,
+    scope: ProviderScope,
 }
 
 #[derive(Debug, Deserialize)]
@@ -26,6 +32,16 @@ struct GitHubRepo {
     html_url: String,
     private: bool,
     owner: GitHubOwner,
+    language: Option<String>,
+    #[serde(default)]
+    topics: Vec<String>,
+    stargazers_count: u64,
+    archived: bool,
+    fork: bool,
+    updated_at: String,
+    created_at: Option<String>,
+    clone_url: Option<String>,
+    mirror_url: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -38,6 +54,12 @@ struct GitHubUser {
     login: String,
 }
 
+#[derive(Debug, Deserialize)]
+struct GitHubAccount {
+    #[serde(rename = "type")]
+    account_type: String,
+}
+
 impl GitHubProvider {
     pub fn new(base_url: String, token: Option<String>, display_name: String) -> Self {
         Self {
@@ -45,9 +67,16 @@ impl GitHubProvider {
             base_url,
             token,
             display_name,
+            scope: ProviderScope::default(),
         }
     }
 
+    /// Apply per-provider defaults from the config
+    pub fn with_scope(mut self, scope: ProviderScope) -> Self {
+        self.scope = scope;
+        self
+    }
+
     async fn get_username(&self) -> Result<String> {
         let token = self
             .token
@@ -86,66 +115,228 @@ impl GitHubProvider {
 
         request
     }
+
+    /// GET `url` and parse the JSON body, turning error responses into API errors
+    async fn get_json<T: serde::de::DeserializeOwned>(&self, url: &str, what: &str) -> Result<T> {
+        Ok(self.get_page(url, what).await?.0)
+    }
+
+    /// Like `get_json`, also returning the URL of the next page, if any
+    async fn get_page<T: serde::de::DeserializeOwned>(
+        &self,
+        url: &str,
+        what: &str,
+    ) -> Result<(T, Option<String>)> {
+        let response = self
+            .build_request(url)
+            .send()
+            .await
+            .with_context(|| format!("Failed to fetch {}", what))?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            return Err(super::api_error(
+                "GitHub",
+                status,
+                &body,
+                self.token.as_deref(),
+            ));
+        }
+
+        let next = next_link(response.headers());
+        let body = response
+            .json()
+            .await
+            .with_context(|| format!("Failed to parse {}", what))?;
+        Ok((body, next))
+    }
+
+    /// `org:` for organizations and `user:` for everyone else
+    async fn owner_qualifier(&self, owner: &str) -> Result<String> {
+        let url = format!("{}/users/{}", self.base_url, urlencoding::encode(owner));
+        let account: GitHubAccount = self.get_json(&url, "GitHub account").await?;
+        Ok(owner_qualifier(owner, &account.account_type))
+    }
+
+    /// Repositories the user is affiliated with. The search API can't
+    /// express these, so the listing is filtered locally, page by page until
+    /// `limit` repositories match, the listing ends or `MAX_PAGES` is reached.
+    async fn search_affiliated(
+        &self,
+        query: &SearchQuery,
+        affiliation: Affiliation,
+        limit: usize,
+    ) -> Result<Vec<Repository>> {
+        if self.token.is_none() {
+            anyhow::bail!("Authentication required to list your GitHub repositories");
+        }
+        super::skip_native("Listing your GitHub repositories", query);
+
+        let (path, params) = affiliation_listing(affiliation);
+        let mut url = format!("{}{}?per_page={}", self.base_url, path, PAGE_SIZE);
+        if let Some(params) = params {
+            url.push('&');
+            url.push_str(params);
+        }
+
+        // Like the search API, listings include archived repositories unless
+        // the query or the provider's config says otherwise
+        let hide_archived = query.archived.is_none() && self.scope.include_archived == Some(false);
+
+        let mut repos = Vec::new();
+        let mut next = Some(url);
+        for _ in 0..MAX_PAGES {
+            let Some(url) = next.take() else { break };
+            let (page, next_url): (Vec<GitHubRepo>, _) =
+                self.get_page(&url, "GitHub repositories").await?;
+            repos.extend(
+                page.into_iter()
+                    .filter(|repo| !(hide_archived && repo.archived))
+                    .map(|repo| self.repository(repo))
+                    .filter(|repo| query.matches_listing(repo)),
+            );
+            if repos.len() >= limit {
+                break;
+            }
+            next = next_url;
+        }
+        repos.truncate(limit);
+        Ok(repos)
+    }
+
+    fn repository(&self, repo: GitHubRepo) -> Repository {
+        Repository {
+            name: repo.name,
+            full_name: repo.full_name,
+            description: repo.description,
+            url: repo.html_url,
+            private: repo.private,
+            provider: self.display_name.clone(),
+            owner: repo.owner.login,
+            language: repo.language,
+            topics: Some(repo.topics),
+            stars: Some(repo.stargazers_count),
+            archived: Some(repo.archived),
+            fork: Some(repo.fork),
+            updated_at: Some(repo.updated_at),
+            created_at: repo.created_at,
+            also_at: Vec::new(),
+            clone_url: repo.clone_url,
+            mirror_of: repo.mirror_url,
+        }
+    }
 }
 
 #[async_trait]
 impl Provider for GitHubProvider {
     async fn search(
         &self,
-        query: &str,
-        mine_only: bool,
+        query: &SearchQuery,
+        mine: Option<Affiliation>,
         owner: Option<&str>,
         limit: usize,
     ) -> Result<Vec<Repository>> {
-        let search_query = if mine_only {
-            let username = self.get_username().await?;
-            format!("{} user:{}", query, username)
-        } else if let Some(owner) = owner {
-            format!("{} user:{}", query, owner)
-        } else {
-            query.to_string()
+        if query.project.is_some() {
+            anyhow::bail!("`project:` is only supported by Bitbucket");
+        }
+        let owner = owner.or(self.scope.owner.as_deref());
+        let owner_qualifier = match mine {
+            // Owned repositories can go through the search API
+            Some(Affiliation::Owned) => Some(format!("user:{}", self.get_username().await?)),
+            Some(affiliation) => return self.search_affiliated(query, affiliation, limit).await,
+            None => match owner {
+                Some(owner) => Some(self.owner_qualifier(owner).await?),
+                None => None,
+            },
         };
 
-        let url = format!(
+        let mut search_query = qualifiers(query, self.scope.include_archived);
+        search_query.extend(owner_qualifier);
+        if let Some(extra) = &self.scope.extra_query {
+            search_query.push(extra.clone());
+        }
+        let search_query = search_query.join(" ");
+
+        let mut url = format!(
             "{}/search/repositories?q={}&per_page={}",
             self.base_url,
             urlencoding::encode(&search_query),
-            limit
+            page_size(limit)
         );
+        // The search API sorts by best match, stars or update time; name and
+        // creation date are sorted after merging
+        let sort = match query.sort.key {
+            SortKey::Stars => Some("stars"),
+            SortKey::Updated => Some("updated"),
+            _ => None,
+        };
+        if let Some(sort) = sort {
+            url.push_str(&format!(
+                "&sort={}&order={}",
+                sort,
+                query.sort.order().as_str()
+            ));
+        }
+
+        let mut repos = Vec::new();
+        let mut next = Some(url);
+        for _ in 0..MAX_PAGES {
+            let Some(url) = next.take() else { break };
+            let (page, next_url): (SearchResponse, _) =
+                self.get_page(&url, "GitHub repositories").await?;
+            repos.extend(
+                page.items
+                    .into_iter()
+                    .map(|repo| self.repository(repo))
+                    .filter(|repo| query.matches(repo)),
+            );
+            if repos.len() >= limit {
+                break;
+            }
+            next = next_url;
+        }
+        repos.truncate(limit);
+        Ok(repos)
+    }
 
+    async fn token_info(&self) -> Result<TokenInfo> {
+        let url = format!("{}/user", self.base_url);
         let response = self
             .build_request(&url)
             .send()
             .await
-            .context("Failed to search GitHub repositories")?;
+            .context("Failed to fetch GitHub user")?;
 
         if !response.status().is_success() {
-            let status = response.status();
-            let body = response.text().await.unwrap_or_default();
-            anyhow::bail!("GitHub API error ({}): {}", status, body);
+            anyhow::bail!("GitHub API error: {}", response.status());
         }
 
-        let search_response: SearchResponse = response
-            .json()
-            .await
-            .context("Failed to parse GitHub response")?;
-
-        let display_name = self.display_name.clone();
-        let repos = search_response
-            .items
-            .into_iter()
-            .map(|repo| Repository {
-                name: repo.name,
-                full_name: repo.full_name,
-                description: repo.description,
-                url: repo.html_url,
-                private: repo.private,
-                provider: display_name.clone(),
-                owner: repo.owner.login,
-            })
-            .collect();
+        let headers = response.headers().clone();
+        let user: GitHubUser = response.json().await?;
 
-        Ok(repos)
+        // Fine-grained and app tokens carry no X-OAuth-Scopes header
+        let scopes = header_list(&headers, "x-oauth-scopes");
+        let mut warnings = Vec::new();
+        if let Some(scopes) = &scopes {
+            if !scopes.iter().any(|scope| scope == "repo") {
+                warnings.push(
+                    "token lacks the `repo` scope; private repositories will not be found"
+                        .to_string(),
+                );
+            }
+        }
+
+        Ok(TokenInfo {
+            username: user.login,
+            scopes,
+            expires_at: headers
+                .get("github-authentication-token-expiration")
+                .and_then(|value| value.to_str().ok())
+                .map(str::to_string),
+            rate_limit_remaining: header_u64(&headers, "x-ratelimit-remaining"),
+            warnings,
+        })
     }
 
     fn name(&self) -> &'static str {
@@ -156,3 +347,149 @@ impl Provider for GitHubProvider {
         self.token.is_some()
     }
 }
+
+/// Search qualifier limiting results to `owner`, given its account type
+fn owner_qualifier(owner: &str, account_type: &str) -> String {
+    let qualifier = if account_type == "Organization" {
+        "org"
+    } else {
+        "user"
+    };
+    format!("{}:{}", qualifier, owner)
+}
+
+/// Listing endpoint and query parameters for the user's repositories of
+/// one affiliation
+fn affiliation_listing(affiliation: Affiliation) -> (&'static str, Option<&'static str>) {
+    match affiliation {
+        Affiliation::Owned => ("/user/repos", Some("affiliation=owner")),
+        Affiliation::Member => ("/user/repos", Some("affiliation=organization_member")),
+        Affiliation::Collaborator => ("/user/repos", Some("affiliation=collaborator")),
+        Affiliation::Any => (
+            "/user/repos",
+            Some("affiliation=owner,collaborator,organization_member"),
+        ),
+        Affiliation::Starred => ("/user/starred", Some("sort=updated")),
+        Affiliation::Watched => ("/user/subscriptions", None),
+    }
+}
+
+/// Translate the query into GitHub search terms and qualifiers. `updated:`
+/// has no repository qualifier and is left to the client-side filter; GitHub's
+/// `pushed:` is a different date and would drop repositories it should keep.
+fn qualifiers(query: &SearchQuery, include_archived: Option<bool>) -> Vec<String> {
+    let mut terms = Vec::new();
+    if !query.text.is_empty() {
+        terms.push(query.text.clone());
+        if !query.fields.is_empty() {
+            let fields: Vec<&str> = query.fields.iter().map(SearchField::as_str).collect();
+            terms.push(format!("in:{}", fields.join(",")));
+        }
+    }
+    if let Some(language) = &query.language {
+        terms.push(format!("language:{}", quote(language)));
+    }
+    for topic in &query.topics {
+        terms.push(format!("topic:{}", quote(topic)));
+    }
+    if let Some(stars) = &query.stars {
+        terms.push(format!("stars:{}", stars));
+    }
+    // GitHub includes archived repositories unless told otherwise
+    match query.archived {
+        Some(archived) => terms.push(format!("archived:{}", archived)),
+        None if include_archived == Some(false) => terms.push("archived:false".to_string()),
+        None => {}
+    }
+    // Forks are already left out by default
+    if query.fork == Some(true) {
+        terms.push("fork:only".to_string());
+    }
+    terms.extend(query.native.iter().cloned());
+    terms
+}
+
+fn quote(value: &str) -> String {
+    if value.contains(char::is_whitespace) {
+        format!("\"{}\"", value)
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_qualifiers() {
+        let query = SearchQuery::parse(
+            "billing in:name,readme lang:\"Jupyter Notebook\" topic:payments stars:>10 fork:true updated:>2025-01-01 is:public",
+        )
+        .unwrap();
+        assert_eq!(
+            qualifiers(&query, Some(false)).join(" "),
+            "billing in:name,readme language:\"Jupyter Notebook\" topic:payments stars:>10 archived:false fork:only is:public"
+        );
+    }
+
+    #[test]
+    fn test_updated_is_checked_against_update_time() {
+        // Settings edited recently, last pushed years ago
+        let repo: GitHubRepo = serde_json::from_value(serde_json::json!({
+            "name": "billing",
+            "full_name": "acme/billing",
+            "description": null,
+            "html_url": "https://github.com/acme/billing",
+            "private": false,
+            "owner": { "login": "acme" },
+            "language": "Rust",
+            "stargazers_count": 3,
+            "archived": false,
+            "fork": false,
+            "updated_at": "2025-06-01T12:00:00Z",
+            "pushed_at": "2020-03-01T12:00:00Z",
+        }))
+        .unwrap();
+        let provider = GitHubProvider::new(
+            "https://api.github.com".to_string(),
+            None,
+            "github".to_string(),
+        );
+        let query = SearchQuery::parse("billing updated:>2025-01-01").unwrap();
+
+        assert!(!qualifiers(&query, None)
+            .iter()
+            .any(|term| term.starts_with("pushed:")));
+        assert!(query.matches(&provider.repository(repo)));
+    }
+
+    #[test]
+    fn test_owner_qualifier() {
+        assert_eq!(owner_qualifier("acme", "Organization"), "org:acme");
+        assert_eq!(owner_qualifier("octocat", "User"), "user:octocat");
+    }
+
+    #[test]
+    fn test_affiliation_listing() {
+        assert_eq!(
+            affiliation_listing(Affiliation::Member),
+            ("/user/repos", Some("affiliation=organization_member"))
+        );
+        assert_eq!(
+            affiliation_listing(Affiliation::Any),
+            (
+                "/user/repos",
+                Some("affiliation=owner,collaborator,organization_member")
+            )
+        );
+        assert_eq!(
+            affiliation_listing(Affiliation::Starred),
+            ("/user/starred", Some("sort=updated"))
+        );
+        assert_eq!(
+            affiliation_listing(Affiliation::Watched),
+            ("/user/subscriptions", None)
+        );
+    }
+}
@@ -3,14 +3,17 @@ use async_trait::async_trait;
 use reqwest::Client;
 use serde::Deserialize;
 
-use super::Provider;
+use super::{header_u64, next_link, page_size, Affiliation, Provider, TokenInfo, MAX_PAGES};
+use crate::config::ProviderScope;
 use crate::models::Repository;
+use crate::query::{next_day, Op, SearchField, SearchQuery, SortKey};
 
 pub struct GitLabProvider {
     client: Client,
     base_url: String,
     token: Option<String>,
     display_name: String,
+    scope: ProviderScope,
 }
 
 #[derive(Debug, Deserialize)]
@@ -21,11 +24,56 @@ struct GitLabProject {
     web_url: String,
     visibility: String,
     namespace: GitLabNamespace,
+    #[serde(default)]
+    topics: Vec<String>,
+    #[serde(default)]
+    star_count: u64,
+    #[serde(default)]
+    archived: bool,
+    forked_from_project: Option<serde_json::Value>,
+    last_activity_at: Option<String>,
+    created_at: Option<String>,
+    http_url_to_repo: Option<String>,
+    /// Only returned to maintainers
+    import_url: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
 struct GitLabNamespace {
     name: String,
+    full_path: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct GitLabUser {
+    username: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct GitLabId {
+    id: u64,
+}
+
+/// Where an owner's projects are listed
+#[derive(Debug, PartialEq)]
+enum OwnerScope {
+    Group(u64),
+    User(u64),
+}
+
+impl OwnerScope {
+    fn projects_path(&self) -> String {
+        match self {
+            OwnerScope::Group(id) => format!("/groups/{}/projects", id),
+            OwnerScope::User(id) => format!("/users/{}/projects", id),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct GitLabTokenSelf {
+    scopes: Vec<String>,
+    expires_at: Option<String>,
 }
 
 impl GitLabProvider {
@@ -35,77 +83,251 @@ impl GitLabProvider {
             base_url,
             token,
             display_name,
+            scope: ProviderScope::default(),
         }
     }
 
+    /// Apply per-provider defaults from the config
+    pub fn with_scope(mut self, scope: ProviderScope) -> Self {
+        self.scope = scope;
+        self
+    }
+
     fn build_request(&self, url: &str) -> reqwest::RequestBuilder {
         let mut request = self.client.get(url).header("User-Agent", "repo_search_cli");
 
+        // Bearer works for both personal access tokens and OAuth tokens
         if let Some(token) = &self.token {
-            request = request.header("PRIVATE-TOKEN", token);
+            request = request.header("Authorization", format!("Bearer {}", token));
         }
 
         request
     }
+
+    fn repository(&self, project: GitLabProject) -> Repository {
+        Repository {
+            name: project.name,
+            full_name: project.path_with_namespace,
+            description: project.description,
+            url: project.web_url,
+            private: project.visibility != "public",
+            provider: self.display_name.clone(),
+            owner: project.namespace.name,
+            // Project listings don't include the language; it's filtered server-side,
+            // and `query_params` refuses it where the endpoint can't
+            language: None,
+            topics: Some(project.topics),
+            stars: Some(project.star_count),
+            archived: Some(project.archived),
+            fork: Some(project.forked_from_project.is_some()),
+            updated_at: project.last_activity_at,
+            created_at: project.created_at,
+            also_at: Vec::new(),
+            clone_url: project.http_url_to_repo,
+            mirror_of: project.import_url,
+        }
+    }
+
+    /// Look the owner up as a group path (including subgroups like
+    /// `acme/platform`), then as a username
+    async fn resolve_owner(&self, owner: &str) -> Result<OwnerScope> {
+        let url = format!(
+            "{}/api/v4/groups/{}",
+            self.base_url,
+            urlencoding::encode(owner)
+        );
+        let response = self
+            .build_request(&url)
+            .send()
+            .await
+            .context("Failed to look up GitLab group")?;
+        if response.status().is_success() {
+            let group: GitLabId = response.json().await?;
+            return Ok(OwnerScope::Group(group.id));
+        }
+        if response.status() != reqwest::StatusCode::NOT_FOUND {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            return Err(super::api_error(
+                "GitLab",
+                status,
+                &body,
+                self.token.as_deref(),
+            ));
+        }
+
+        let url = format!(
+            "{}/api/v4/users?username={}",
+            self.base_url,
+            urlencoding::encode(owner)
+        );
+        let users: Vec<GitLabId> = self
+            .build_request(&url)
+            .send()
+            .await
+            .context("Failed to look up GitLab user")?
+            .error_for_status()
+            .context("Failed to look up GitLab user")?
+            .json()
+            .await?;
+        match users.first() {
+            Some(user) => Ok(OwnerScope::User(user.id)),
+            None => anyhow::bail!("No GitLab group or user named '{}'", owner),
+        }
+    }
 }
 
 #[async_trait]
 impl Provider for GitLabProvider {
     async fn search(
         &self,
-        query: &str,
-        mine_only: bool,
+        query: &SearchQuery,
+        mine: Option<Affiliation>,
         owner: Option<&str>,
         limit: usize,
     ) -> Result<Vec<Repository>> {
+        if query.project.is_some() {
+            anyhow::bail!("`project:` is only supported by Bitbucket");
+        }
+        if query.fields.contains(&SearchField::Readme) {
+            anyhow::bail!("GitLab can't search README contents; `in:readme` is not supported");
+        }
+        super::skip_native("GitLab", query);
+
+        let owner = if mine.is_some() {
+            None
+        } else {
+            owner.or(self.scope.owner.as_deref())
+        };
+
+        let owner_scope = match owner {
+            Some(owner) => Some(self.resolve_owner(owner).await?),
+            None => None,
+        };
+        let projects_path = owner_scope
+            .as_ref()
+            .map_or_else(|| "/projects".to_string(), OwnerScope::projects_path);
         let mut url = format!(
-            "{}/api/v4/projects?search={}&per_page={}",
+            "{}/api/v4{}?per_page={}",
             self.base_url,
-            urlencoding::encode(query),
-            limit
+            projects_path,
+            page_size(limit)
         );
 
-        if mine_only {
-            url.push_str("&owned=true");
+        if matches!(owner_scope, Some(OwnerScope::Group(_))) {
+            url.push_str("&include_subgroups=true");
+        }
+        match mine {
+            Some(Affiliation::Owned) => url.push_str("&owned=true"),
+            // GitLab doesn't tell members and collaborators apart
+            Some(Affiliation::Member | Affiliation::Collaborator | Affiliation::Any) => {
+                url.push_str("&membership=true")
+            }
+            Some(Affiliation::Starred) => url.push_str("&starred=true"),
+            Some(Affiliation::Watched) => {
+                anyhow::bail!("GitLab has no watched projects; `--mine=watched` is not supported")
+            }
+            None => {}
+        }
+        for (key, value) in query_params(query, self.scope.include_archived, owner_scope.as_ref())?
+        {
+            url.push_str(&format!("&{}={}", key, urlencoding::encode(&value)));
         }
+        if let Some(extra) = &self.scope.extra_query {
+            url.push('&');
+            url.push_str(extra.trim_start_matches(['?', '&']));
+        }
+
+        let mut repos = Vec::new();
+        let mut next = Some(url);
+        for _ in 0..MAX_PAGES {
+            let Some(url) = next.take() else { break };
+            let response = self
+                .build_request(&url)
+                .send()
+                .await
+                .context("Failed to search GitLab projects")?;
+
+            if !response.status().is_success() {
+                let status = response.status();
+                let body = response.text().await.unwrap_or_default();
+                return Err(super::api_error(
+                    "GitLab",
+                    status,
+                    &body,
+                    self.token.as_deref(),
+                ));
+            }
 
+            let next_url = next_link(response.headers());
+            let projects: Vec<GitLabProject> = response
+                .json()
+                .await
+                .context("Failed to parse GitLab response")?;
+
+            repos.extend(
+                projects
+                    .into_iter()
+                    .filter(|project| {
+                        owner.is_none_or(|owner| in_namespace(&project.namespace, owner))
+                    })
+                    .map(|project| self.repository(project))
+                    .filter(|repo| query.matches(repo)),
+            );
+            if repos.len() >= limit {
+                break;
+            }
+            next = next_url;
+        }
+        repos.truncate(limit);
+        Ok(repos)
+    }
+
+    async fn token_info(&self) -> Result<TokenInfo> {
+        let url = format!("{}/api/v4/user", self.base_url);
         let response = self
             .build_request(&url)
             .send()
             .await
-            .context("Failed to search GitLab projects")?;
+            .context("Failed to fetch GitLab user")?;
 
         if !response.status().is_success() {
-            let status = response.status();
-            let body = response.text().await.unwrap_or_default();
-            anyhow::bail!("GitLab API error ({}): {}", status, body);
+            anyhow::bail!("GitLab API error: {}", response.status());
         }
 
-        let projects: Vec<GitLabProject> = response
-            .json()
-            .await
-            .context("Failed to parse GitLab response")?;
-
-        let display_name = self.display_name.clone();
-        let repos = projects
-            .into_iter()
-            .filter(|project| {
-                owner.map_or(true, |owner| {
-                    project.namespace.name.eq_ignore_ascii_case(owner)
-                })
-            })
-            .map(|project| Repository {
-                name: project.name,
-                full_name: project.path_with_namespace.clone(),
-                description: project.description,
-                url: project.web_url,
-                private: project.visibility != "public",
-                provider: display_name.clone(),
-                owner: project.namespace.name,
-            })
-            .collect();
+        let rate_limit_remaining = header_u64(response.headers(), "ratelimit-remaining");
+        let user: GitLabUser = response.json().await?;
 
-        Ok(repos)
+        // Only personal/project/group access tokens can describe themselves;
+        // OAuth tokens get a 404 here
+        let url = format!("{}/api/v4/personal_access_tokens/self", self.base_url);
+        let token_self = match self.build_request(&url).send().await {
+            Ok(response) if response.status().is_success() => {
+                response.json::<GitLabTokenSelf>().await.ok()
+            }
+            _ => None,
+        };
+
+        let mut warnings = Vec::new();
+        if let Some(token_self) = &token_self {
+            let can_read = token_self
+                .scopes
+                .iter()
+                .any(|scope| scope == "api" || scope == "read_api");
+            if !can_read {
+                warnings.push(
+                    "token lacks the `read_api` scope; project searches will fail".to_string(),
+                );
+            }
+        }
+
+        Ok(TokenInfo {
+            username: user.username,
+            expires_at: token_self.as_ref().and_then(|t| t.expires_at.clone()),
+            scopes: token_self.map(|t| t.scopes),
+            rate_limit_remaining,
+            warnings,
+        })
     }
 
     fn name(&self) -> &'static str {
@@ -116,3 +338,197 @@ impl Provider for GitLabProvider {
         self.token.is_some()
     }
 }
+
+/// Whether the namespace is `owner` or one of its subgroups
+fn in_namespace(namespace: &GitLabNamespace, owner: &str) -> bool {
+    let path = namespace.full_path.to_ascii_lowercase();
+    let owner = owner.trim_matches('/').to_ascii_lowercase();
+    path == owner || path.starts_with(&format!("{}/", owner))
+}
+
+/// Translate the query into parameters for the endpoint listing the
+/// projects: `/projects` without an owner, otherwise the group's or user's
+/// projects. Those endpoints take fewer filters; what they can't apply is
+/// left to the client-side filter when the project carries the field, and
+/// refused otherwise. Stars and forks have no parameter anywhere.
+fn query_params(
+    query: &SearchQuery,
+    include_archived: Option<bool>,
+    owner_scope: Option<&OwnerScope>,
+) -> Result<Vec<(&'static str, String)>> {
+    let group = matches!(owner_scope, Some(OwnerScope::Group(_)));
+    let user = matches!(owner_scope, Some(OwnerScope::User(_)));
+    let mut params = Vec::new();
+    // `search` covers names, paths and descriptions; a topics-only search
+    // becomes a topic filter. Narrowing to `in:` fields happens client-side.
+    let topics_only = query.fields == [SearchField::Topics];
+    let mut topics = query.topics.clone();
+    if topics_only {
+        topics.extend(query.text.split_whitespace().map(str::to_ascii_lowercase));
+    } else if !query.text.is_empty() {
+        params.push(("search", query.text.clone()));
+    }
+    if let Some(language) = &query.language {
+        // Projects don't list their languages, so this can't be checked here
+        if group {
+            anyhow::bail!(
+                "GitLab can't filter a group's projects by language; drop `lang:` or the owner"
+            );
+        }
+        params.push(("with_programming_language", language.clone()));
+    }
+    // A user's projects have no topic filter; topics are checked client-side
+    if !topics.is_empty() && !user {
+        params.push(("topic", topics.join(",")));
+    }
+    if let Some(archived) = query.archived.or(include_archived) {
+        params.push(("archived", archived.to_string()));
+    }
+    let order_by = match query.sort.key {
+        // Best match needs a search term; otherwise keep GitLab's default
+        SortKey::Relevance
+            if owner_scope.is_none() && params.iter().any(|(key, _)| *key == "search") =>
+        {
+            Some("similarity")
+        }
+        SortKey::Relevance => None,
+        SortKey::Stars => Some("star_count"),
+        SortKey::Updated => Some("last_activity_at"),
+        SortKey::Name => Some("name"),
+        SortKey::Created => Some("created_at"),
+    };
+    if let Some(order_by) = order_by {
+        params.push(("order_by", order_by.to_string()));
+        params.push(("sort", query.sort.order().as_str().to_string()));
+    }
+    // Only `/projects` filters by activity; owner listings are checked
+    // client-side against `last_activity_at`
+    if let (Some(updated), None) = (&query.updated, owner_scope) {
+        // The API takes timestamps; a date covers the whole day up to the next midnight
+        let start = format!("{}T00:00:00Z", updated.value);
+        let end = format!("{}T00:00:00Z", next_day(&updated.value));
+        match updated.op {
+            Op::Ge => params.push(("last_activity_after", start)),
+            Op::Gt => params.push(("last_activity_after", end)),
+            Op::Lt => params.push(("last_activity_before", start)),
+            Op::Le => params.push(("last_activity_before", end)),
+            Op::Eq => {
+                params.push(("last_activity_after", start));
+                params.push(("last_activity_before", end));
+            }
+        }
+    }
+    Ok(params)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::query::Sort;
+
+    #[test]
+    fn test_in_namespace() {
+        let namespace = GitLabNamespace {
+            name: "Platform Team".to_string(),
+            full_path: "acme/platform".to_string(),
+        };
+        assert!(in_namespace(&namespace, "acme"));
+        assert!(in_namespace(&namespace, "ACME/platform"));
+        assert!(!in_namespace(&namespace, "acme/plat"));
+        assert!(!in_namespace(&namespace, "Platform Team"));
+    }
+
+    #[test]
+    fn test_owner_projects_path() {
+        assert_eq!(OwnerScope::Group(7).projects_path(), "/groups/7/projects");
+        assert_eq!(OwnerScope::User(9).projects_path(), "/users/9/projects");
+    }
+
+    #[test]
+    fn test_query_params() {
+        let query = SearchQuery::parse(
+            "billing lang:rust topic:payments topic:api stars:>10 updated:>2025-01-01",
+        )
+        .unwrap();
+        assert_eq!(
+            query_params(&query, Some(false), None).unwrap(),
+            vec![
+                ("search", "billing".to_string()),
+                ("with_programming_language", "rust".to_string()),
+                ("topic", "payments,api".to_string()),
+                ("archived", "false".to_string()),
+                ("order_by", "similarity".to_string()),
+                ("sort", "desc".to_string()),
+                ("last_activity_after", "2025-01-02T00:00:00Z".to_string()),
+            ]
+        );
+
+        let mut query = SearchQuery::parse("Payments in:topics").unwrap();
+        query.sort = Sort {
+            key: SortKey::Name,
+            order: None,
+        };
+        assert_eq!(
+            query_params(&query, None, None).unwrap(),
+            vec![
+                ("topic", "payments".to_string()),
+                ("order_by", "name".to_string()),
+                ("sort", "asc".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_owner_scoped_params() {
+        let query =
+            SearchQuery::parse("billing topic:payments updated:>2025-01-01 archived:true").unwrap();
+        assert_eq!(
+            query_params(&query, None, Some(&OwnerScope::Group(7))).unwrap(),
+            vec![
+                ("search", "billing".to_string()),
+                ("topic", "payments".to_string()),
+                ("archived", "true".to_string()),
+            ]
+        );
+        assert_eq!(
+            query_params(&query, None, Some(&OwnerScope::User(9))).unwrap(),
+            vec![
+                ("search", "billing".to_string()),
+                ("archived", "true".to_string()),
+            ]
+        );
+
+        let query = SearchQuery::parse("billing lang:go").unwrap();
+        assert!(query_params(&query, None, Some(&OwnerScope::Group(7))).is_err());
+        assert_eq!(
+            query_params(&query, None, Some(&OwnerScope::User(9))).unwrap(),
+            vec![
+                ("search", "billing".to_string()),
+                ("with_programming_language", "go".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_updated_covers_whole_days() {
+        let params = |updated: &str| {
+            let query = SearchQuery::parse(&format!("updated:{}", updated)).unwrap();
+            query_params(&query, None, None).unwrap()
+        };
+        assert_eq!(
+            params("<=2025-01-01"),
+            vec![("last_activity_before", "2025-01-02T00:00:00Z".to_string())]
+        );
+        assert_eq!(
+            params("<2025-01-01"),
+            vec![("last_activity_before", "2025-01-01T00:00:00Z".to_string())]
+        );
+        assert_eq!(
+            params("2024-02-28"),
+            vec![
+                ("last_activity_after", "2024-02-28T00:00:00Z".to_string()),
+                ("last_activity_before", "2024-02-29T00:00:00Z".to_string()),
+            ]
+        );
+    }
+}
@@ -1,21 +1,71 @@
 pub mod bitbucket;
+pub mod detect;
 pub mod github;
 pub mod gitlab;
 
 use anyhow::Result;
 use async_trait::async_trait;
 
+use crate::config::{ProviderType, ResolvedProvider};
 use crate::models::Repository;
+use crate::query::SearchQuery;
+use crate::security;
+
+/// Which of the user's repositories `--mine` covers
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Affiliation {
+    /// Repositories you own
+    Owned,
+    /// Repositories of groups, organizations or workspaces you belong to
+    Member,
+    /// Repositories you were added to as a collaborator
+    Collaborator,
+    /// Repositories you starred
+    Starred,
+    /// Repositories you watch
+    Watched,
+    /// Any repository you can reach as owner, collaborator or member
+    Any,
+}
+
+impl Affiliation {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Affiliation::Owned => "owned",
+            Affiliation::Member => "member",
+            Affiliation::Collaborator => "collaborator",
+            Affiliation::Starred => "starred",
+            Affiliation::Watched => "watched",
+            Affiliation::Any => "any",
+        }
+    }
+}
+
+/// What the provider reports about the token in use
+#[derive(Debug, Clone, Default)]
+pub struct TokenInfo {
+    pub username: String,
+    /// Granted scopes, if the provider reports them for this kind of token
+    pub scopes: Option<Vec<String>>,
+    pub expires_at: Option<String>,
+    pub rate_limit_remaining: Option<u64>,
+    pub warnings: Vec<String>,
+}
 
 #[async_trait]
 pub trait Provider: Send + Sync {
+    /// Up to `limit` repositories matching `query`. Qualifiers the API can't
+    /// express are checked page by page, fetching more pages to make up for
+    /// what they filter out.
     async fn search(
         &self,
-        query: &str,
-        mine_only: bool,
+        query: &SearchQuery,
+        mine: Option<Affiliation>,
         owner: Option<&str>,
         limit: usize,
     ) -> Result<Vec<Repository>>;
+    /// Look up the authenticated user and details of the token
+    async fn token_info(&self) -> Result<TokenInfo>;
     #[allow(dead_code)]
     fn name(&self) -> &'static str;
     #[allow(dead_code)]
@@ -25,3 +75,132 @@ pub trait Provider: Send + Sync {
 pub use bitbucket::BitbucketProvider;
 pub use github::GitHubProvider;
 pub use gitlab::GitLabProvider;
+
+/// Build the client for a resolved provider
+pub fn build(provider: &ResolvedProvider) -> Box<dyn Provider> {
+    let url = provider.url.clone();
+    let token = provider.token.clone();
+    let name = provider.name.clone();
+    let scope = provider.scope.clone();
+
+    match provider.provider_type {
+        ProviderType::Github => Box::new(GitHubProvider::new(url, token, name).with_scope(scope)),
+        ProviderType::Gitlab => Box::new(GitLabProvider::new(url, token, name).with_scope(scope)),
+        ProviderType::Bitbucket => {
+            Box::new(BitbucketProvider::new(url, token, name).with_scope(scope))
+        }
+    }
+}
+
+/// Most results the provider APIs return per page
+const PAGE_SIZE: usize = 100;
+
+/// Pages fetched at most per search, so a selective client-side filter
+/// doesn't walk a whole listing
+const MAX_PAGES: usize = 10;
+
+/// Page size to request when `limit` results are wanted
+fn page_size(limit: usize) -> usize {
+    limit.clamp(1, PAGE_SIZE)
+}
+
+/// URL of the next page from a `Link` header, as GitHub and GitLab send it
+fn next_link(headers: &reqwest::header::HeaderMap) -> Option<String> {
+    let value = headers.get("link")?.to_str().ok()?;
+    value.split(',').find_map(|link| {
+        let (url, params) = link.split_once(';')?;
+        params
+            .split(';')
+            .any(|param| param.trim() == "rel=\"next\"")
+            .then(|| url.trim().trim_matches(['<', '>']).to_string())
+    })
+}
+
+/// Read a numeric response header such as a remaining rate limit
+fn header_u64(headers: &reqwest::header::HeaderMap, name: &str) -> Option<u64> {
+    headers.get(name)?.to_str().ok()?.trim().parse().ok()
+}
+
+/// Warn that `provider` skips the query's provider-native qualifiers, which
+/// only GitHub understands
+fn skip_native(provider: &str, query: &SearchQuery) {
+    if !query.native.is_empty() {
+        eprintln!(
+            "Warning: {} doesn't support `{}`; ignoring it",
+            provider,
+            query.native.join(" ")
+        );
+    }
+}
+
+/// Split a comma-separated scopes header (e.g. `X-OAuth-Scopes`)
+fn header_list(headers: &reqwest::header::HeaderMap, name: &str) -> Option<Vec<String>> {
+    let value = headers.get(name)?.to_str().ok()?;
+    Some(
+        value
+            .split(',')
+            .map(str::trim)
+            .filter(|scope| !scope.is_empty())
+            .map(str::to_string)
+            .collect(),
+    )
+}
+
+/// Error for a failed API response, keeping the provider's message (or the
+/// start of the body) but never the token
+fn api_error(
+    service: &str,
+    status: reqwest::StatusCode,
+    body: &str,
+    token: Option<&str>,
+) -> anyhow::Error {
+    let message = serde_json::from_str::<serde_json::Value>(body)
+        .ok()
+        .and_then(|json| {
+            json.get("message")
+                .or_else(|| json.pointer("/error/message"))
+                .or_else(|| json.get("error"))
+                .and_then(|value| value.as_str())
+                .map(str::to_string)
+        })
+        .unwrap_or_else(|| body.chars().take(200).collect());
+    let secrets: Vec<&str> = token.into_iter().collect();
+    let message = security::redact(message.trim(), &secrets);
+
+    if message.is_empty() {
+        anyhow::anyhow!("{} API error ({})", service, status)
+    } else {
+        anyhow::anyhow!("{} API error ({}): {}", service, status, message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_next_link() {
+        let mut headers = reqwest::header::HeaderMap::new();
+        assert_eq!(next_link(&headers), None);
+
+        headers.insert(
+            "link",
+            "<https://api.github.com/search/repositories?q=x&page=2>; rel=\"next\", \
+             <https://api.github.com/search/repositories?q=x&page=10>; rel=\"last\""
+                .parse()
+                .unwrap(),
+        );
+        assert_eq!(
+            next_link(&headers).as_deref(),
+            Some("https://api.github.com/search/repositories?q=x&page=2")
+        );
+
+        headers.insert(
+            "link",
+            "<https://gitlab.com/api/v4/projects?page=1>; rel=\"first\""
+                .parse()
+                .unwrap(),
+        );
+        assert_eq!(next_link(&headers), None);
+    }
+}
@@ -0,0 +1,802 @@
+use std::cmp::Ordering;
+use std::fmt;
+
+use thiserror::Error;
+
+use crate::config::levenshtein;
+use crate::models::Repository;
+
+/// Qualifiers understood by the query language
+const QUALIFIERS: &[&str] = &[
+    "in", "lang", "language", "topic", "stars", "archived", "fork", "owner", "user", "org",
+    "project", "updated",
+];
+
+/// GitHub's own repository search qualifiers, passed through as written.
+/// Any other `key:value` term is searched as text.
+const NATIVE_QUALIFIERS: &[&str] = &[
+    "is",
+    "license",
+    "size",
+    "created",
+    "pushed",
+    "forks",
+    "followers",
+    "topics",
+    "mirror",
+    "template",
+    "has",
+    "repo",
+    "good-first-issues",
+    "help-wanted-issues",
+];
+
+/// Errors raised while parsing a search query
+#[derive(Debug, Error, PartialEq)]
+pub enum QueryError {
+    #[error(
+        "Unknown qualifier `{key}:`{}; quote the term to search for it literally",
+        suggestion.as_ref().map(|s| format!(" (did you mean `{}:`?)", s)).unwrap_or_default()
+    )]
+    UnknownQualifier {
+        key: String,
+        suggestion: Option<String>,
+    },
+    #[error("Invalid value `{value}` for `{key}:`; expected {expected}")]
+    InvalidValue {
+        key: String,
+        value: String,
+        expected: &'static str,
+    },
+    #[error("Qualifier `{key}:` was given more than once")]
+    Repeated { key: String },
+    #[error("Unterminated quote in search query")]
+    UnterminatedQuote,
+    #[error("Search query is empty")]
+    Empty,
+}
+
+/// Repository field the free text is matched against, from `--in` or `in:`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum SearchField {
+    Name,
+    Description,
+    Topics,
+    Readme,
+}
+
+impl SearchField {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SearchField::Name => "name",
+            SearchField::Description => "description",
+            SearchField::Topics => "topics",
+            SearchField::Readme => "readme",
+        }
+    }
+
+    fn parse(value: &str) -> Option<Self> {
+        match value.to_ascii_lowercase().as_str() {
+            "name" => Some(SearchField::Name),
+            "description" => Some(SearchField::Description),
+            "topics" | "topic" => Some(SearchField::Topics),
+            "readme" => Some(SearchField::Readme),
+            _ => None,
+        }
+    }
+}
+
+/// What results are ordered by, from `--sort`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum SortKey {
+    #[default]
+    Relevance,
+    Stars,
+    Updated,
+    Name,
+    Created,
+}
+
+/// Sort direction, from `--order`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum SortOrder {
+    Asc,
+    Desc,
+}
+
+impl SortOrder {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SortOrder::Asc => "asc",
+            SortOrder::Desc => "desc",
+        }
+    }
+}
+
+/// Requested ordering, sent to each provider and applied again to the merged results
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Sort {
+    pub key: SortKey,
+    pub order: Option<SortOrder>,
+}
+
+impl Sort {
+    /// The given order, or names A-Z and everything else highest or newest first
+    pub fn order(&self) -> SortOrder {
+        self.order.unwrap_or(match self.key {
+            SortKey::Name => SortOrder::Asc,
+            _ => SortOrder::Desc,
+        })
+    }
+
+    /// Sort merged results. The sort is stable, so ties keep their provider
+    /// order; repositories missing the value go last either way.
+    pub fn apply(&self, repos: &mut [Repository]) {
+        let order = self.order();
+        // Timestamps differ in precision and offset format between providers
+        let timestamp = |value: &Option<String>| {
+            value
+                .as_deref()
+                .map(|value| value.get(..19).unwrap_or(value).to_string())
+        };
+        match self.key {
+            SortKey::Relevance => {}
+            SortKey::Stars => repos.sort_by(|a, b| compare(a.stars, b.stars, order)),
+            SortKey::Updated => repos
+                .sort_by(|a, b| compare(timestamp(&a.updated_at), timestamp(&b.updated_at), order)),
+            SortKey::Created => repos
+                .sort_by(|a, b| compare(timestamp(&a.created_at), timestamp(&b.created_at), order)),
+            SortKey::Name => repos.sort_by(|a, b| {
+                compare(
+                    Some(a.name.to_lowercase()),
+                    Some(b.name.to_lowercase()),
+                    order,
+                )
+            }),
+        }
+    }
+}
+
+fn compare<T: Ord>(a: Option<T>, b: Option<T>, order: SortOrder) -> Ordering {
+    match (a, b) {
+        (Some(a), Some(b)) if order == SortOrder::Asc => a.cmp(&b),
+        (Some(a), Some(b)) => b.cmp(&a),
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => Ordering::Equal,
+    }
+}
+
+/// Comparison operator in `stars:>10` or `updated:<=2025-01-01`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Op {
+    Eq,
+    Gt,
+    Ge,
+    Lt,
+    Le,
+}
+
+impl Op {
+    /// Split a leading operator off `value`
+    fn split(value: &str) -> (Op, &str) {
+        for (prefix, op) in [(">=", Op::Ge), ("<=", Op::Le), (">", Op::Gt), ("<", Op::Lt)] {
+            if let Some(rest) = value.strip_prefix(prefix) {
+                return (op, rest);
+            }
+        }
+        (Op::Eq, value)
+    }
+
+    fn holds<T: PartialOrd + ?Sized>(&self, actual: &T, expected: &T) -> bool {
+        match self {
+            Op::Eq => actual == expected,
+            Op::Gt => actual > expected,
+            Op::Ge => actual >= expected,
+            Op::Lt => actual < expected,
+            Op::Le => actual <= expected,
+        }
+    }
+}
+
+impl fmt::Display for Op {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Op::Eq => "",
+            Op::Gt => ">",
+            Op::Ge => ">=",
+            Op::Lt => "<",
+            Op::Le => "<=",
+        })
+    }
+}
+
+/// A value compared with an operator, e.g. `>10`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Comparison<T> {
+    pub op: Op,
+    pub value: T,
+}
+
+impl<T: PartialOrd> Comparison<T> {
+    pub fn matches(&self, actual: &T) -> bool {
+        self.op.holds(actual, &self.value)
+    }
+}
+
+impl<T: fmt::Display> fmt::Display for Comparison<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}", self.op, self.value)
+    }
+}
+
+/// A search parsed from the query language, e.g.
+/// `billing lang:rust topic:payments stars:>10 archived:false owner:acme updated:>2025-01-01 -fork`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SearchQuery {
+    /// Free-text terms, space-separated
+    pub text: String,
+    /// Fields the text is matched against; empty for each provider's default
+    pub fields: Vec<SearchField>,
+    pub language: Option<String>,
+    pub topics: Vec<String>,
+    pub stars: Option<Comparison<u64>>,
+    pub archived: Option<bool>,
+    /// `Some(false)` for `-fork`, `Some(true)` for forks only
+    pub fork: Option<bool>,
+    pub owner: Option<String>,
+    /// Bitbucket project key
+    pub project: Option<String>,
+    /// Compared against the last update, as a `YYYY-MM-DD` date
+    pub updated: Option<Comparison<String>>,
+    /// GitHub's own qualifiers, such as `is:public` or `license:mit`, kept
+    /// as written for the provider
+    pub native: Vec<String>,
+    /// From `--sort` and `--order` rather than the query text
+    pub sort: Sort,
+}
+
+impl SearchQuery {
+    pub fn parse(input: &str) -> Result<Self, QueryError> {
+        let mut query = SearchQuery::default();
+        let mut text = Vec::new();
+
+        for (token, quoted) in tokenize(input)? {
+            if quoted {
+                text.push(token);
+                continue;
+            }
+            if let Some(key) = token.strip_prefix('-') {
+                match key {
+                    "fork" => set_once(&mut query.fork, false, "fork")?,
+                    "archived" => set_once(&mut query.archived, false, "archived")?,
+                    _ => text.push(token),
+                }
+                continue;
+            }
+
+            let Some((key, value)) = token.split_once(':') else {
+                text.push(token);
+                continue;
+            };
+            // Things like `rust:web`, `c++:` or URLs are not qualifiers
+            let key = key.to_ascii_lowercase();
+            if value.starts_with("//") {
+                text.push(token);
+            } else if QUALIFIERS.contains(&key.as_str()) {
+                query.apply(&key, value)?;
+            } else if NATIVE_QUALIFIERS.contains(&key.as_str()) {
+                query.native.push(token);
+            } else {
+                text.push(token);
+            }
+        }
+
+        query.text = text.join(" ");
+        if query == SearchQuery::default() {
+            return Err(QueryError::Empty);
+        }
+        Ok(query)
+    }
+
+    /// Apply one qualifier as if it appeared in the query as `key:value`
+    pub fn apply(&mut self, key: &str, value: &str) -> Result<(), QueryError> {
+        let invalid = |expected| QueryError::InvalidValue {
+            key: key.to_string(),
+            value: value.to_string(),
+            expected,
+        };
+        if value.is_empty() {
+            return Err(invalid("a value"));
+        }
+
+        match key {
+            "in" => {
+                if !self.fields.is_empty() {
+                    return Err(QueryError::Repeated {
+                        key: key.to_string(),
+                    });
+                }
+                self.fields = value
+                    .split(',')
+                    .map(SearchField::parse)
+                    .collect::<Option<_>>()
+                    .ok_or_else(|| invalid("name, description, topics or readme"))?;
+                Ok(())
+            }
+            "lang" | "language" => set_once(&mut self.language, value.to_string(), "language"),
+            "topic" => {
+                self.topics.push(value.to_ascii_lowercase());
+                Ok(())
+            }
+            "stars" => {
+                let (op, number) = Op::split(value);
+                let value = number
+                    .parse()
+                    .map_err(|_| invalid("a number such as 10, >10 or <=100"))?;
+                set_once(&mut self.stars, Comparison { op, value }, "stars")
+            }
+            "archived" | "fork" => {
+                let flag = match value {
+                    "true" | "yes" | "only" => true,
+                    "false" | "no" => false,
+                    _ => return Err(invalid("true or false")),
+                };
+                let target = if key == "archived" {
+                    &mut self.archived
+                } else {
+                    &mut self.fork
+                };
+                set_once(target, flag, key)
+            }
+            "owner" | "user" | "org" => set_once(&mut self.owner, value.to_string(), "owner"),
+            "project" => set_once(&mut self.project, value.to_string(), "project"),
+            "updated" => {
+                let (op, date) = Op::split(value);
+                if !is_date(date) {
+                    return Err(invalid("a date such as >2025-01-01"));
+                }
+                let updated = Comparison {
+                    op,
+                    value: date.to_string(),
+                };
+                set_once(&mut self.updated, updated, "updated")
+            }
+            _ => Err(QueryError::UnknownQualifier {
+                key: key.to_string(),
+                suggestion: QUALIFIERS
+                    .iter()
+                    .map(|candidate| (levenshtein(key, candidate), candidate))
+                    .filter(|(distance, _)| *distance <= 2)
+                    .min_by_key(|(distance, _)| *distance)
+                    .map(|(_, candidate)| candidate.to_string()),
+            }),
+        }
+    }
+
+    /// Whether `repo` passes the qualifiers. Metadata a provider does not
+    /// report is treated as unknown and does not exclude the repository.
+    pub fn matches(&self, repo: &Repository) -> bool {
+        let language = match (&self.language, &repo.language) {
+            (Some(wanted), Some(actual)) => wanted.eq_ignore_ascii_case(actual),
+            _ => true,
+        };
+        let topics = repo.topics.as_ref().is_none_or(|topics| {
+            self.topics.iter().all(|wanted| {
+                topics
+                    .iter()
+                    .any(|topic| topic.eq_ignore_ascii_case(wanted))
+            })
+        });
+        let stars = match (&self.stars, repo.stars) {
+            (Some(stars), Some(actual)) => stars.matches(&actual),
+            _ => true,
+        };
+        let archived = match (self.archived, repo.archived) {
+            (Some(wanted), Some(actual)) => wanted == actual,
+            _ => true,
+        };
+        let fork = match (self.fork, repo.fork) {
+            (Some(wanted), Some(actual)) => wanted == actual,
+            _ => true,
+        };
+        let updated = match (&self.updated, &repo.updated_at) {
+            // ISO 8601 timestamps compare correctly as strings on their date part
+            (Some(updated), Some(actual)) => {
+                updated.matches(&actual.get(..10).unwrap_or(actual).to_string())
+            }
+            _ => true,
+        };
+
+        // Without `in:` the provider decides what the text matches
+        let text = self.fields.is_empty() || self.matches_text(repo, &self.fields);
+
+        text && language && topics && stars && archived && fork && updated
+    }
+
+    /// Like `matches`, but also checks the text against names and descriptions
+    /// (or the `in:` fields). For listings the API can't search, such as a
+    /// user's repositories on GitHub.
+    pub fn matches_listing(&self, repo: &Repository) -> bool {
+        let fields: &[SearchField] = if self.fields.is_empty() {
+            &[SearchField::Name, SearchField::Description]
+        } else {
+            &self.fields
+        };
+        self.matches_text(repo, fields) && self.matches(repo)
+    }
+
+    /// Whether every text term appears in one of `fields`. README contents are
+    /// never fetched, so `readme` always matches.
+    fn matches_text(&self, repo: &Repository, fields: &[SearchField]) -> bool {
+        if fields.contains(&SearchField::Readme) {
+            return true;
+        }
+        if fields.contains(&SearchField::Topics) && repo.topics.is_none() {
+            return true;
+        }
+
+        let mut haystacks = Vec::new();
+        for field in fields {
+            match field {
+                SearchField::Name => haystacks.push(repo.name.to_lowercase()),
+                SearchField::Description => {
+                    haystacks.extend(repo.description.as_ref().map(|d| d.to_lowercase()))
+                }
+                SearchField::Topics => haystacks.extend(
+                    repo.topics
+                        .iter()
+                        .flatten()
+                        .map(|topic| topic.to_lowercase()),
+                ),
+                SearchField::Readme => {}
+            }
+        }
+        self.text.split_whitespace().all(|term| {
+            let term = term.to_lowercase();
+            haystacks.iter().any(|haystack| haystack.contains(&term))
+        })
+    }
+}
+
+fn set_once<T>(slot: &mut Option<T>, value: T, key: &str) -> Result<(), QueryError> {
+    if slot.is_some() {
+        return Err(QueryError::Repeated {
+            key: key.to_string(),
+        });
+    }
+    *slot = Some(value);
+    Ok(())
+}
+
+/// `YYYY-MM-DD`, with the month and day in range
+fn is_date(value: &str) -> bool {
+    let parts: Vec<&str> = value.split('-').collect();
+    let [y, m, d] = parts.as_slice() else {
+        return false;
+    };
+    if y.len() != 4
+        || m.len() != 2
+        || d.len() != 2
+        || !parts
+            .iter()
+            .all(|part| part.chars().all(|c| c.is_ascii_digit()))
+    {
+        return false;
+    }
+    let (Ok(year), Ok(month), Ok(day)) = (y.parse(), m.parse(), d.parse()) else {
+        return false;
+    };
+    (1..=12).contains(&month) && (1..=days_in_month(year, month)).contains(&day)
+}
+
+fn days_in_month(year: u32, month: u32) -> u32 {
+    let leap = (year.is_multiple_of(4) && !year.is_multiple_of(100)) || year.is_multiple_of(400);
+    match month {
+        2 if leap => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// The day after a `YYYY-MM-DD` date
+pub(crate) fn next_day(date: &str) -> String {
+    let mut parts = date
+        .splitn(3, '-')
+        .map(|part| part.parse::<u32>().unwrap_or(0));
+    let (mut year, mut month, mut day) = (
+        parts.next().unwrap_or(0),
+        parts.next().unwrap_or(1),
+        parts.next().unwrap_or(1),
+    );
+    day += 1;
+    if day > days_in_month(year, month) {
+        day = 1;
+        month += 1;
+    }
+    if month > 12 {
+        month = 1;
+        year += 1;
+    }
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+/// Split on whitespace, keeping `"quoted phrases"` together. Each token is
+/// returned with whether it was a quoted phrase (and so never a qualifier).
+fn tokenize(input: &str) -> Result<Vec<(String, bool)>, QueryError> {
+    let mut tokens = Vec::new();
+    let mut chars = input.chars().peekable();
+
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+            continue;
+        }
+        if c == '"' {
+            chars.next();
+            let mut phrase = String::new();
+            loop {
+                match chars.next() {
+                    Some('"') => break,
+                    Some(c) => phrase.push(c),
+                    None => return Err(QueryError::UnterminatedQuote),
+                }
+            }
+            tokens.push((phrase, true));
+            continue;
+        }
+
+        // A quote inside a token quotes a qualifier value: lang:"Jupyter Notebook"
+        let mut token = String::new();
+        while let Some(&c) = chars.peek() {
+            if c.is_whitespace() {
+                break;
+            }
+            chars.next();
+            if c != '"' {
+                token.push(c);
+                continue;
+            }
+            loop {
+                match chars.next() {
+                    Some('"') => break,
+                    Some(c) => token.push(c),
+                    None => return Err(QueryError::UnterminatedQuote),
+                }
+            }
+        }
+        tokens.push((token, false));
+    }
+
+    Ok(tokens)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn repo() -> Repository {
+        Repository {
+            name: "billing".to_string(),
+            owner: "acme".to_string(),
+            provider: "github".to_string(),
+            url: "https://github.com/acme/billing".to_string(),
+            full_name: "acme/billing".to_string(),
+            language: Some("Rust".to_string()),
+            topics: Some(vec!["payments".to_string()]),
+            stars: Some(42),
+            archived: Some(false),
+            fork: Some(false),
+            updated_at: Some("2025-03-01T10:00:00Z".to_string()),
+            created_at: Some("2020-06-01T08:00:00Z".to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_parse_full_query() {
+        let query = SearchQuery::parse(
+            "billing lang:rust topic:payments stars:>10 archived:false owner:acme updated:>2025-01-01 -fork",
+        )
+        .unwrap();
+        assert_eq!(query.text, "billing");
+        assert_eq!(query.language.as_deref(), Some("rust"));
+        assert_eq!(query.topics, vec!["payments".to_string()]);
+        assert_eq!(
+            query.stars,
+            Some(Comparison {
+                op: Op::Gt,
+                value: 10
+            })
+        );
+        assert_eq!(query.archived, Some(false));
+        assert_eq!(query.fork, Some(false));
+        assert_eq!(query.owner.as_deref(), Some("acme"));
+        assert_eq!(query.updated.unwrap().to_string(), ">2025-01-01");
+    }
+
+    #[test]
+    fn test_parse_quoted_value() {
+        let query = SearchQuery::parse("lang:\"Jupyter Notebook\" \"two words\"").unwrap();
+        assert_eq!(query.language.as_deref(), Some("Jupyter Notebook"));
+        assert_eq!(query.text, "two words");
+    }
+
+    #[test]
+    fn test_parse_keeps_plain_terms() {
+        let query = SearchQuery::parse("\"lang:rust\" c++ https://x.io -flag").unwrap();
+        assert_eq!(query.text, "lang:rust c++ https://x.io -flag");
+        assert_eq!(query.language, None);
+    }
+
+    #[test]
+    fn test_parse_native_qualifiers() {
+        let query =
+            SearchQuery::parse("billing is:public License:mit good-first-issues:>2 lang:rust")
+                .unwrap();
+        assert_eq!(query.text, "billing");
+        assert_eq!(
+            query.native,
+            vec!["is:public", "License:mit", "good-first-issues:>2"]
+        );
+        assert_eq!(query.language.as_deref(), Some("rust"));
+        assert_eq!(
+            SearchQuery::default().apply("langauge", "rust"),
+            Err(QueryError::UnknownQualifier {
+                key: "langauge".to_string(),
+                suggestion: Some("language".to_string()),
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_unknown_keys_as_text() {
+        let query = SearchQuery::parse("rust:web c++:templates https://x.io").unwrap();
+        assert_eq!(query.text, "rust:web c++:templates https://x.io");
+        assert!(query.native.is_empty());
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(matches!(
+            SearchQuery::parse("stars:lots"),
+            Err(QueryError::InvalidValue { .. })
+        ));
+        assert!(matches!(
+            SearchQuery::parse("updated:>yesterday"),
+            Err(QueryError::InvalidValue { .. })
+        ));
+        for date in [
+            "2025-13-01",
+            "2025-00-10",
+            "2025-02-29",
+            "2025-04-31",
+            "2025-1-01",
+        ] {
+            assert!(
+                SearchQuery::parse(&format!("updated:>{}", date)).is_err(),
+                "{}",
+                date
+            );
+        }
+        assert!(SearchQuery::parse("updated:2024-02-29").is_ok());
+        assert!(matches!(
+            SearchQuery::parse("lang:go lang:rust"),
+            Err(QueryError::Repeated { .. })
+        ));
+        assert_eq!(SearchQuery::parse("  "), Err(QueryError::Empty));
+        assert_eq!(
+            SearchQuery::parse("\"open"),
+            Err(QueryError::UnterminatedQuote)
+        );
+    }
+
+    #[test]
+    fn test_parse_fields() {
+        let query = SearchQuery::parse("billing in:name,description").unwrap();
+        assert_eq!(
+            query.fields,
+            vec![SearchField::Name, SearchField::Description]
+        );
+        assert!(matches!(
+            SearchQuery::parse("billing in:body"),
+            Err(QueryError::InvalidValue { .. })
+        ));
+    }
+
+    #[test]
+    fn test_matches_fields() {
+        let mut described = repo();
+        described.description = Some("Invoices and payouts".to_string());
+
+        assert!(SearchQuery::parse("bill in:name")
+            .unwrap()
+            .matches(&described));
+        assert!(!SearchQuery::parse("invoices in:name")
+            .unwrap()
+            .matches(&described));
+        assert!(SearchQuery::parse("invoices in:name,description")
+            .unwrap()
+            .matches(&described));
+        assert!(SearchQuery::parse("payments in:topics")
+            .unwrap()
+            .matches(&described));
+        // Nothing to check README matches against
+        assert!(SearchQuery::parse("anything in:readme")
+            .unwrap()
+            .matches(&described));
+
+        let listed = SearchQuery::parse("payouts lang:rust").unwrap();
+        assert!(listed.matches(&repo()));
+        assert!(!listed.matches_listing(&repo()));
+        assert!(listed.matches_listing(&described));
+    }
+
+    #[test]
+    fn test_sort() {
+        let named = |name: &str, stars: Option<u64>, updated: &str| Repository {
+            name: name.to_string(),
+            stars,
+            updated_at: Some(updated.to_string()),
+            ..repo()
+        };
+        let mut repos = vec![
+            named("beta", Some(5), "2025-01-02T00:00:00.000Z"),
+            named("Alpha", None, "2025-01-03T00:00:00+00:00"),
+            named("gamma", Some(50), "2025-01-01T00:00:00Z"),
+        ];
+        let names = |repos: &[Repository]| {
+            repos
+                .iter()
+                .map(|repo| repo.name.clone())
+                .collect::<Vec<_>>()
+        };
+
+        let mut sort = Sort {
+            key: SortKey::Stars,
+            order: None,
+        };
+        sort.apply(&mut repos);
+        assert_eq!(names(&repos), ["gamma", "beta", "Alpha"]);
+
+        sort.order = Some(SortOrder::Asc);
+        sort.apply(&mut repos);
+        assert_eq!(names(&repos), ["beta", "gamma", "Alpha"]);
+
+        sort = Sort {
+            key: SortKey::Name,
+            order: None,
+        };
+        sort.apply(&mut repos);
+        assert_eq!(names(&repos), ["Alpha", "beta", "gamma"]);
+
+        sort.key = SortKey::Updated;
+        sort.apply(&mut repos);
+        assert_eq!(names(&repos), ["Alpha", "beta", "gamma"]);
+    }
+
+    #[test]
+    fn test_matches() {
+        let query =
+            SearchQuery::parse("lang:rust topic:payments stars:>=42 updated:>2025-01-01 -fork")
+                .unwrap();
+        assert!(query.matches(&repo()));
+
+        let mut forked = repo();
+        forked.fork = Some(true);
+        assert!(!query.matches(&forked));
+
+        let mut stale = repo();
+        stale.updated_at = Some("2024-12-31T23:59:59Z".to_string());
+        assert!(!query.matches(&stale));
+
+        // Unknown metadata does not exclude
+        let mut sparse = repo();
+        sparse.language = None;
+        sparse.stars = None;
+        assert!(SearchQuery::parse("lang:go stars:>1000")
+            .unwrap()
+            .matches(&sparse));
+    }
+}
@@ -0,0 +1,238 @@
+use std::collections::HashMap;
+
+use crate::models::Repository;
+use crate::query::SearchQuery;
+
+/// One provider's results, in the provider's own best-match order
+pub struct ProviderResults {
+    pub repos: Vec<Repository>,
+    /// Multiplier from the provider's `weight` setting
+    pub weight: f64,
+}
+
+/// Merge results from several providers into one list, best match first.
+/// Each provider ranks differently and reports popularity on its own scale,
+/// so repositories are scored on how well the name and description match the
+/// query, with stars, activity and the provider's own order normalised within
+/// each provider. A single provider's results keep their order.
+pub fn merge(query: &SearchQuery, results: Vec<ProviderResults>) -> Vec<Repository> {
+    if results.iter().filter(|r| !r.repos.is_empty()).count() <= 1 {
+        return results.into_iter().flat_map(|r| r.repos).collect();
+    }
+
+    let terms = tokens(&query.text);
+    let mut scored = Vec::new();
+    for results in results {
+        let max_stars = results.repos.iter().filter_map(|r| r.stars).max();
+        let mut updated: Vec<&str> = results
+            .repos
+            .iter()
+            .filter_map(|r| r.updated_at.as_deref())
+            .collect();
+        updated.sort_unstable();
+
+        let count = results.repos.len();
+        let scores: Vec<f64> = results
+            .repos
+            .iter()
+            .enumerate()
+            .map(|(position, repo)| {
+                let score = text_score(&query.text, &terms, repo)
+                    + 0.2 * popularity(repo.stars, max_stars)
+                    + 0.1 * activity(repo.updated_at.as_deref(), &updated)
+                    + 0.2 * (1.0 - position as f64 / count as f64);
+                score * results.weight
+            })
+            .collect();
+        scored.extend(scores.into_iter().zip(results.repos));
+    }
+
+    // Stable, so equal scores keep provider order
+    scored.sort_by(|(a, _), (b, _)| b.total_cmp(a));
+    scored.into_iter().map(|(_, repo)| repo).collect()
+}
+
+/// Keep the best `limit` results while giving each provider an even share.
+/// Results stay in rank order; a provider only gets more than its share when
+/// the others don't have enough results to fill theirs.
+pub fn take_fair(repos: Vec<Repository>, limit: usize) -> Vec<Repository> {
+    if repos.len() <= limit {
+        return repos;
+    }
+
+    let mut providers: Vec<&str> = Vec::new();
+    for repo in &repos {
+        if !providers.contains(&repo.provider.as_str()) {
+            providers.push(&repo.provider);
+        }
+    }
+    let share = limit.div_ceil(providers.len());
+
+    // First pass: each provider's best results up to its share. Second pass:
+    // the best of the rest until the limit is reached.
+    let mut taken = vec![false; repos.len()];
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    let mut remaining = limit;
+    for (index, repo) in repos.iter().enumerate() {
+        let count = counts.entry(&repo.provider).or_default();
+        if remaining > 0 && *count < share {
+            *count += 1;
+            taken[index] = true;
+            remaining -= 1;
+        }
+    }
+    for flag in taken.iter_mut().filter(|taken| !**taken) {
+        if remaining == 0 {
+            break;
+        }
+        *flag = true;
+        remaining -= 1;
+    }
+
+    repos
+        .into_iter()
+        .zip(taken)
+        .filter_map(|(repo, taken)| taken.then_some(repo))
+        .collect()
+}
+
+/// How well the name and description match the query text
+fn text_score(text: &str, terms: &[String], repo: &Repository) -> f64 {
+    if terms.is_empty() {
+        return 0.0;
+    }
+    let name = repo.name.to_lowercase();
+    let joined = terms.join("-");
+
+    let mut score = 0.0;
+    if name == text.to_lowercase() || tokens(&name).join("-") == joined {
+        score += 1.0;
+    } else if name.starts_with(&terms[0]) {
+        score += 0.5;
+    }
+
+    let name_tokens = tokens(&name);
+    let description_tokens = tokens(repo.description.as_deref().unwrap_or_default());
+    let overlap = |words: &[String]| {
+        terms.iter().filter(|term| words.contains(term)).count() as f64 / terms.len() as f64
+    };
+    score + 0.6 * overlap(&name_tokens) + 0.3 * overlap(&description_tokens)
+}
+
+/// Stars on a log scale relative to the provider's most starred result
+fn popularity(stars: Option<u64>, max_stars: Option<u64>) -> f64 {
+    match (stars, max_stars) {
+        (Some(stars), Some(max)) if max > 0 => (stars as f64).ln_1p() / (max as f64).ln_1p(),
+        _ => 0.0,
+    }
+}
+
+/// 1.0 for the provider's most recently updated result down to 0.0 for the oldest
+fn activity(updated_at: Option<&str>, sorted: &[&str]) -> f64 {
+    match updated_at {
+        Some(updated_at) if sorted.len() > 1 => {
+            let rank = sorted.partition_point(|other| *other < updated_at);
+            rank as f64 / (sorted.len() - 1) as f64
+        }
+        _ => 0.0,
+    }
+}
+
+/// Lowercase words, splitting on anything that isn't a letter or digit
+fn tokens(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn repo(name: &str, description: &str, provider: &str, stars: Option<u64>) -> Repository {
+        Repository {
+            name: name.to_string(),
+            owner: "acme".to_string(),
+            provider: provider.to_string(),
+            url: format!("https://example.com/acme/{}", name),
+            full_name: format!("acme/{}", name),
+            description: Some(description.to_string()),
+            stars,
+            ..Default::default()
+        }
+    }
+
+    fn names(repos: &[Repository]) -> Vec<&str> {
+        repos.iter().map(|repo| repo.name.as_str()).collect()
+    }
+
+    #[test]
+    fn test_exact_name_beats_description_mentions() {
+        let query = SearchQuery::parse("billing service").unwrap();
+        let github = ProviderResults {
+            repos: vec![
+                repo(
+                    "awesome-go",
+                    "Includes a billing service example",
+                    "github",
+                    Some(90000),
+                ),
+                repo("shop", "Billing and service tools", "github", Some(5000)),
+            ],
+            weight: 1.0,
+        };
+        let gitlab = ProviderResults {
+            repos: vec![repo("billing-service", "", "work-gitlab", Some(3))],
+            weight: 1.0,
+        };
+
+        let merged = merge(&query, vec![github, gitlab]);
+        assert_eq!(names(&merged), ["billing-service", "awesome-go", "shop"]);
+    }
+
+    #[test]
+    fn test_take_fair() {
+        let mut repos: Vec<Repository> = (0..6)
+            .map(|i| repo(&format!("gh{}", i), "", "github", None))
+            .collect();
+        repos.push(repo("gl0", "", "gitlab", None));
+        repos.push(repo("bb0", "", "bitbucket", None));
+        repos.push(repo("bb1", "", "bitbucket", None));
+
+        // Shares of 2: github can't take the whole page
+        assert_eq!(
+            names(&take_fair(repos.clone(), 6)),
+            ["gh0", "gh1", "gh2", "gl0", "bb0", "bb1"]
+        );
+        assert_eq!(names(&take_fair(repos.clone(), 3)), ["gh0", "gl0", "bb0"]);
+        assert_eq!(take_fair(repos, 20).len(), 9);
+    }
+
+    #[test]
+    fn test_weight_and_single_provider() {
+        let query = SearchQuery::parse("parser").unwrap();
+        let results = vec![
+            ProviderResults {
+                repos: vec![repo("parser", "", "github", None)],
+                weight: 1.0,
+            },
+            ProviderResults {
+                repos: vec![repo("parser", "", "gitlab", None)],
+                weight: 2.0,
+            },
+        ];
+        let merged = merge(&query, results);
+        assert_eq!(merged[0].provider, "gitlab");
+
+        // A lone provider keeps its own order
+        let single = ProviderResults {
+            repos: vec![
+                repo("other", "", "github", None),
+                repo("parser", "", "github", None),
+            ],
+            weight: 1.0,
+        };
+        assert_eq!(names(&merge(&query, vec![single])), ["other", "parser"]);
+    }
+}
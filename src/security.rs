@@ -0,0 +1,178 @@
+use std::path::Path;
+
+use reqwest::Url;
+
+/// Placeholder shown instead of a secret
+pub const REDACTED: &str = "<redacted>";
+
+/// Replace every occurrence of `secrets` in `text` with `REDACTED`
+pub fn redact(text: &str, secrets: &[&str]) -> String {
+    let mut redacted = text.to_string();
+    for secret in secrets {
+        // Very short values would mangle ordinary words without hiding anything
+        if secret.len() >= 4 {
+            redacted = redacted.replace(secret, REDACTED);
+        }
+    }
+    redacted
+}
+
+/// Permission bits that let other users read `path`, if any
+#[cfg(unix)]
+pub fn insecure_mode(path: &Path) -> Option<u32> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let mode = std::fs::metadata(path).ok()?.permissions().mode() & 0o777;
+    (mode & 0o077 != 0).then_some(mode)
+}
+
+#[cfg(not(unix))]
+pub fn insecure_mode(_path: &Path) -> Option<u32> {
+    None
+}
+
+/// Write `content` to `path`, readable only by the user. A new file is
+/// created with mode 0600 and an existing one is restricted before the
+/// content goes in, so the secret is never readable by others.
+pub fn write_private(path: &Path, content: impl AsRef<[u8]>) -> std::io::Result<()> {
+    use std::io::Write;
+
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options.open(path)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        file.set_permissions(std::fs::Permissions::from_mode(0o600))?;
+    }
+    file.write_all(content.as_ref())
+}
+
+/// Whether a token configured for `configured_url` may be sent to `target_url`:
+/// only when both have the same scheme, host and port, or the target host is
+/// trusted and reached over the configured scheme
+pub fn may_send_token(configured_url: &str, target_url: &str, trusted_hosts: &[String]) -> bool {
+    let (Ok(configured), Ok(target)) = (Url::parse(configured_url), Url::parse(target_url)) else {
+        return false;
+    };
+    let Some(target_host) = target.host_str() else {
+        return false;
+    };
+    if configured.scheme() != target.scheme() {
+        return false;
+    }
+    let same_origin = configured
+        .host_str()
+        .is_some_and(|host| host.eq_ignore_ascii_case(target_host))
+        && configured.port_or_known_default() == target.port_or_known_default();
+    same_origin
+        || trusted_hosts
+            .iter()
+            .any(|trusted| trusted.eq_ignore_ascii_case(target_host))
+}
+
+/// Lowercased host of `url`, if it parses
+pub fn host(url: &str) -> Option<String> {
+    Url::parse(url)
+        .ok()?
+        .host_str()
+        .map(|host| host.to_ascii_lowercase())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redact() {
+        let message = "401: {\"message\":\"Bad credentials\",\"token\":\"ghp_secret123\"}";
+        assert_eq!(
+            redact(message, &["ghp_secret123"]),
+            "401: {\"message\":\"Bad credentials\",\"token\":\"<redacted>\"}"
+        );
+        assert_eq!(redact("a bad token", &["a"]), "a bad token");
+    }
+
+    #[test]
+    fn test_may_send_token() {
+        let trusted = vec!["mirror.acme.com".to_string()];
+        assert!(may_send_token(
+            "https://gitlab.acme.com",
+            "https://GitLab.acme.com/sub",
+            &[]
+        ));
+        assert!(!may_send_token(
+            "https://gitlab.acme.com",
+            "https://evil.example.com",
+            &[]
+        ));
+        assert!(may_send_token(
+            "https://gitlab.acme.com",
+            "https://mirror.acme.com",
+            &trusted
+        ));
+        assert!(!may_send_token(
+            "https://gitlab.acme.com",
+            "not a url",
+            &trusted
+        ));
+
+        // Same host, but plaintext or another port
+        assert!(!may_send_token(
+            "https://gitlab.acme.com",
+            "http://gitlab.acme.com",
+            &[]
+        ));
+        assert!(!may_send_token(
+            "https://gitlab.acme.com",
+            "https://gitlab.acme.com:8443",
+            &[]
+        ));
+        assert!(may_send_token(
+            "https://gitlab.acme.com",
+            "https://gitlab.acme.com:443/api",
+            &[]
+        ));
+        assert!(!may_send_token(
+            "https://gitlab.acme.com",
+            "http://mirror.acme.com",
+            &trusted
+        ));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_insecure_mode() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let path = std::env::temp_dir().join(format!("repo_search-mode-{}", std::process::id()));
+        std::fs::write(&path, "").unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o644)).unwrap();
+        assert_eq!(insecure_mode(&path), Some(0o644));
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o600)).unwrap();
+        assert_eq!(insecure_mode(&path), None);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_write_private() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let path = std::env::temp_dir().join(format!("repo_search-private-{}", std::process::id()));
+        write_private(&path, "token = \"a\"").unwrap();
+        assert_eq!(insecure_mode(&path), None);
+
+        // An existing readable file is restricted as well as rewritten
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o644)).unwrap();
+        write_private(&path, "b").unwrap();
+        assert_eq!(insecure_mode(&path), None);
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "b");
+        std::fs::remove_file(&path).unwrap();
+    }
+}